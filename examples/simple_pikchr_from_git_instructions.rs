@@ -19,9 +19,7 @@ fn main() {
         "###,
     )
     .unwrap();
-    let view = View::from_state(&state);
-    println!(
-        "{}",
-        print_pikchr(&view, &LayoutOptions::default()).unwrap()
-    );
+    let options = LayoutOptions::default();
+    let view = View::from_state(&state, &options);
+    println!("{}", print_pikchr(&view, &options).unwrap());
 }
@@ -0,0 +1,140 @@
+use super::layout::{Layout, Swimlane};
+use super::point::Point;
+use super::rect::Rect;
+use super::text::text_height;
+use crate::model::{Diagram, Repository};
+use crate::options::layout::{LayoutDirection, LayoutOptions};
+use crate::view::{aligned_rows, View};
+
+impl Layout {
+    // Layout the repositories of a diagram next to each other, each in its own swimlane.
+    // Commits with the same id are placed in the same row in all repositories.
+    pub fn from_diagram(diagram: &Diagram, options: &LayoutOptions) -> Layout {
+        let padding = options.font_size;
+        let title_height = text_height(options.font_size) + padding;
+        let repositories = diagram
+            .repositories
+            .iter()
+            .map(|(_, r)| r)
+            .collect::<Vec<&Repository>>();
+        let rows = aligned_rows(&repositories, &options.row_mode);
+        let views = repositories
+            .iter()
+            .map(|r| View::from_state_with_rows(r, options, &rows))
+            .collect::<Vec<View>>();
+        // The same distance between rows everywhere keeps the rows aligned
+        let hist_dist = views
+            .iter()
+            .map(|v| Layout::hist_dist(v, options))
+            .fold(0.0, f64::max);
+        let layouts = views
+            .iter()
+            .map(|v| Layout::build(v, options, Some(hist_dist)))
+            .collect::<Vec<Layout>>();
+        // All swimlanes cover the whole history
+        let history = layouts
+            .iter()
+            .map(|l| l.bounds)
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0));
+
+        let mut combined = Layout {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            labels: Vec::new(),
+            brackets: Vec::new(),
+            callouts: Vec::new(),
+            groups: Vec::new(),
+            swimlanes: Vec::new(),
+            legend: None,
+            titles: Vec::new(),
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
+        };
+        let mut offset = 0.0;
+        for ((name, _), mut layout) in diagram.repositories.iter().zip(layouts) {
+            let content = layout.bounds;
+            let rect = match options.graph_direction {
+                LayoutDirection::UP => Rect::new(
+                    content.x - padding,
+                    history.y - padding,
+                    content.width + 2.0 * padding,
+                    history.height + 2.0 * padding + title_height,
+                ),
+                LayoutDirection::RIGHT => Rect::new(
+                    history.x - padding,
+                    content.y - padding,
+                    history.width + 2.0 * padding,
+                    content.height + 2.0 * padding + title_height,
+                ),
+            };
+            layout.swimlanes.push(Swimlane {
+                title: name.clone(),
+                rect,
+                title_rect: Rect::new(rect.x, rect.top() - title_height, rect.width, title_height),
+            });
+            // Place the swimlanes next to each other, across the lanes
+            let shift = match options.graph_direction {
+                LayoutDirection::UP => Point::new(offset - rect.x, 0.0),
+                LayoutDirection::RIGHT => Point::new(0.0, -offset - rect.top()),
+            };
+            layout.translate(shift);
+            offset += padding
+                + match options.graph_direction {
+                    LayoutDirection::UP => rect.width,
+                    LayoutDirection::RIGHT => rect.height,
+                };
+            combined.append(layout);
+        }
+        combined.add_legend_and_titles(options);
+        combined
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn aligned_swimlanes() {
+        // Setup
+        let mut upstream = Repository::default();
+        upstream.commit("A".to_string());
+        upstream.commit("B".to_string());
+        let mut fork = Repository::default();
+        fork.commit("A".to_string());
+        fork.commit("X".to_string());
+        fork.commit("B".to_string());
+        let mut diagram = Diagram::default();
+        diagram.add_repository("upstream", upstream);
+        diagram.add_repository("fork", fork);
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_diagram(&diagram, &options);
+
+        // Test
+        assert_eq!(layout.swimlanes.len(), 2);
+        let (upstream, fork) = (&layout.swimlanes[0], &layout.swimlanes[1]);
+        assert_eq!(upstream.title, "upstream");
+        assert!(upstream.rect.right() < fork.rect.x);
+        assert_eq!(upstream.rect.y, fork.rect.y);
+        assert_eq!(upstream.rect.height, fork.rect.height);
+        let centers = |id: &str| {
+            layout
+                .nodes
+                .iter()
+                .filter(|n| n.id == id)
+                .map(|n| n.center)
+                .collect::<Vec<Point>>()
+        };
+        let a = centers("A");
+        assert_eq!(a.len(), 2);
+        assert_eq!(a[0].y, a[1].y);
+        assert!(upstream.rect.x < a[0].x && a[0].x < upstream.rect.right());
+        assert!(fork.rect.x < a[1].x && a[1].x < fork.rect.right());
+        // B comes after X in the fork, so also in upstream
+        let b = centers("B");
+        assert_eq!(b[0].y, b[1].y);
+        assert!(b[0].y > centers("X")[0].y);
+    }
+}
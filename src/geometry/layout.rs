@@ -0,0 +1,1243 @@
+use super::legend::{Legend, Title};
+use super::path::Path;
+use super::point::Point;
+use super::rect::Rect;
+use super::routing::{route_edge, EdgeKind};
+use super::text::{text_height, text_width};
+use crate::model::{DETACHED_HEAD, STYLE_COLLAPSED, STYLE_ELIDED, STYLE_MOVED};
+use crate::options::layout::{LabelStacking, LayoutDirection, LayoutOptions, SquashDisplay};
+use crate::view::{Commit, Head, View};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+// Number of lines a curve is approximated with in the edge polylines
+const CURVE_STEPS: usize = 8;
+
+// A commit, drawn as a circle
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub center: Point,
+    pub radius: f64,
+    pub style: String,
+}
+
+// Connector from a commit to one of its parents,
+// or the arrow from where a moved ref was to where it is now
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Edge {
+    // Id of the commit
+    pub from: String,
+    // Id of the parent, which is not drawn if it has been left out
+    pub to: String,
+    pub in_lane: bool,
+    // The exact shape of the connector
+    pub path: Path,
+    // The connector approximated by straight lines
+    pub points: Vec<Point>,
+    pub style: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LabelKind {
+    Branch,
+    // The branch that is checked out
+    CheckedOut,
+    // The HEAD marker, pointing to the checked out branch or commit
+    Head,
+    // A remote-tracking branch like `origin/main`
+    Remote,
+    Tag,
+    // The number of commits a collapsed run stands for
+    Collapsed,
+}
+
+// Label attached to a commit: a branch, HEAD or tag pointing to it, or what it stands for
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Label {
+    pub text: String,
+    pub commit: String,
+    pub kind: LabelKind,
+    pub rect: Rect,
+    // Line from the commit to the label, empty if the label
+    // is placed next to another label of the same commit.
+    // For the HEAD marker, it is an arrow from the marker to the commit or branch label.
+    pub connector: Vec<Point>,
+}
+
+// Bracket beside the commits that were squashed into another one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bracket {
+    // Id of the commit they were squashed into
+    pub text: String,
+    pub commit: String,
+    // The bracket, from the first to the last squashed commit
+    pub points: Vec<Point>,
+    pub label: Rect,
+}
+
+// Note on a commit, in a box left of the lanes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Callout {
+    pub text: String,
+    pub commit: String,
+    pub rect: Rect,
+    // Line from the commit to the box, around the lanes in between
+    pub leader: Vec<Point>,
+}
+
+// Shaded box around a run of commits of a group in one lane, named at its top.
+// A group with commits in several lanes, or with others in between, gets several.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupBox {
+    pub name: String,
+    pub rect: Rect,
+    pub label: Rect,
+}
+
+// A bordered area around the graph of one repository of a diagram
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Swimlane {
+    pub title: String,
+    pub rect: Rect,
+    // Area at the top of the swimlane for the title
+    pub title_rect: Rect,
+}
+
+// Size of a label, and the space to leave before it
+struct LabelSize {
+    width: f64,
+    height: f64,
+    gap: f64,
+}
+
+// Everything positioned, in abstract units.
+// Printers only have to draw it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    pub labels: Vec<Label>,
+    pub brackets: Vec<Bracket>,
+    pub callouts: Vec<Callout>,
+    pub groups: Vec<GroupBox>,
+    pub swimlanes: Vec<Swimlane>,
+    pub legend: Option<Legend>,
+    pub titles: Vec<Title>,
+    pub bounds: Rect,
+}
+
+impl Layout {
+    pub fn from_view(view: &View, options: &LayoutOptions) -> Layout {
+        let mut layout = Layout::build(view, options, None);
+        layout.add_legend_and_titles(options);
+        layout
+    }
+
+    // The text in the commit, collapsed runs are only marked as such
+    fn node_label(commit: &Commit) -> String {
+        if commit.collapsed.is_empty() {
+            commit.id.clone()
+        } else {
+            "…".to_string()
+        }
+    }
+
+    // Commits are made large enough for their label
+    fn commit_radii<'a>(view: &'a View, options: &LayoutOptions) -> HashMap<&'a String, f64> {
+        let padding = options.font_size / 2.0;
+        let mut radii: HashMap<&String, f64> = HashMap::new();
+        for lane in &view.lanes {
+            for commit in &lane.commits {
+                let label = Layout::node_label(commit);
+                let radius = text_width(&label, options.font_size) / 2.0 + padding;
+                radii.insert(&commit.id, radius.max(options.commit_radius as f64));
+            }
+        }
+        radii
+    }
+
+    // Lanes and rows are spaced further apart by as much as commits have grown
+    fn growth(radii: &HashMap<&String, f64>, options: &LayoutOptions) -> f64 {
+        let max_radius = radii
+            .values()
+            .fold(options.commit_radius as f64, |a, &b| a.max(b));
+        2.0 * (max_radius - options.commit_radius as f64)
+    }
+
+    // Distance between two rows, so that all commits of the view fit
+    pub(crate) fn hist_dist(view: &View, options: &LayoutOptions) -> f64 {
+        let radii = Layout::commit_radii(view, options);
+        options.commit_hist_dist as f64 + Layout::growth(&radii, options)
+    }
+
+    // Layout a view, optionally with a given distance between rows
+    pub(crate) fn build(view: &View, options: &LayoutOptions, hist_dist: Option<f64>) -> Layout {
+        let font_size = options.font_size;
+        let padding = font_size / 2.0;
+        let radii = Layout::commit_radii(view, options);
+        let growth = Layout::growth(&radii, options);
+        let branch_dist = options.branch_dist as f64 + growth;
+        let hist_dist = hist_dist.unwrap_or(options.commit_hist_dist as f64 + growth);
+        // Positions are computed with lanes as vertical columns
+        // and then turned in the direction of the graph
+        let orient = |p: Point| match options.graph_direction {
+            LayoutDirection::UP => p,
+            LayoutDirection::RIGHT => Point::new(p.y, -p.x),
+        };
+
+        // The commits
+        let mut nodes = Vec::new();
+        let mut positions: HashMap<&String, Point> = HashMap::new();
+        for lane in &view.lanes {
+            for commit in &lane.commits {
+                let position =
+                    Point::new(lane.col as f64 * branch_dist, commit.row as f64 * hist_dist);
+                positions.insert(&commit.id, position);
+                nodes.push(Node {
+                    id: commit.id.clone(),
+                    label: Layout::node_label(commit),
+                    center: orient(position),
+                    radius: radii[&commit.id],
+                    style: if commit.collapsed.is_empty() {
+                        commit.style.clone()
+                    } else {
+                        STYLE_COLLAPSED.to_string()
+                    },
+                });
+            }
+        }
+
+        // The connectors to the parents
+        let mut edges = Vec::new();
+        // The connectors as lines with lanes as vertical columns, to route other lines around
+        let mut connector_lines: Vec<Vec<Point>> = Vec::new();
+        for lane in &view.lanes {
+            for commit in &lane.commits {
+                for parent in &commit.parents {
+                    let to = match positions.get(&parent.commit.id) {
+                        Some(&to) => to,
+                        None => continue,
+                    };
+                    let from = positions[&commit.id];
+                    let path = if parent.in_lane {
+                        Path::new(from).line_to(to)
+                    } else {
+                        let kind = match (parent.begins_lane, parent.ends_lane) {
+                            (true, false) => EdgeKind::BranchOff,
+                            (false, true) => EdgeKind::Merge,
+                            _ => EdgeKind::Direct,
+                        };
+                        route_edge(from, to, &kind, &options.edge_routing)
+                    };
+                    let path = path.chop(radii[&commit.id], radii[&parent.commit.id]);
+                    connector_lines.push(path.flatten(CURVE_STEPS));
+                    let path = path.map(orient);
+                    edges.push(Edge {
+                        from: commit.id.clone(),
+                        to: parent.commit.id.clone(),
+                        in_lane: parent.in_lane,
+                        points: path.flatten(CURVE_STEPS),
+                        path,
+                        style: parent.style.clone(),
+                    });
+                }
+            }
+        }
+
+        // Short connectors towards the parents that have been left out,
+        // the first in the lane unless the commit has a parent there
+        for lane in &view.lanes {
+            for commit in &lane.commits {
+                let first = usize::from(!commit.parents.is_empty());
+                for (i, parent) in commit.elided_parents.iter().enumerate() {
+                    let from = positions[&commit.id];
+                    let offset = (first + i) as f64 * branch_dist / 2.0;
+                    let path = Path::new(from)
+                        .line_to(from + Point::new(offset, -hist_dist / 2.0))
+                        .chop(radii[&commit.id], 0.0)
+                        .map(orient);
+                    edges.push(Edge {
+                        from: commit.id.clone(),
+                        to: parent.clone(),
+                        in_lane: first + i == 0,
+                        points: path.flatten(CURVE_STEPS),
+                        path,
+                        style: STYLE_ELIDED.to_string(),
+                    });
+                }
+            }
+        }
+
+        // The refs that moved
+        for moved in &view.moved_refs {
+            let path = Path::new(positions[&moved.from.id])
+                .line_to(positions[&moved.to.id])
+                .chop(radii[&moved.from.id], radii[&moved.to.id])
+                .map(orient);
+            edges.push(Edge {
+                from: moved.from.id.clone(),
+                to: moved.to.id.clone(),
+                in_lane: false,
+                points: path.flatten(CURVE_STEPS),
+                path,
+                style: STYLE_MOVED.to_string(),
+            });
+        }
+
+        // The branch labels, right of the last used column
+        let mut labels: Vec<Label> = Vec::new();
+        let label_col = view.lanes.iter().map(|l| l.col + 1).max().unwrap_or(0);
+        for lane in &view.lanes {
+            for commit in &lane.commits {
+                let mut entries: Vec<(String, LabelKind)> = Vec::new();
+                // A detached HEAD points to the commit directly
+                if let Some(Head::Detached(head)) = &view.head {
+                    if head.id == commit.id {
+                        entries.push((DETACHED_HEAD.to_string(), LabelKind::Head));
+                    }
+                }
+                // Otherwise it follows the checked out branch
+                for branch in view
+                    .commits_branch_heads
+                    .get(&commit.id)
+                    .into_iter()
+                    .flatten()
+                {
+                    if branch.checked_out {
+                        entries.push((branch.name.clone(), LabelKind::CheckedOut));
+                        entries.push((DETACHED_HEAD.to_string(), LabelKind::Head));
+                    } else if branch.remote.is_some() {
+                        entries.push((branch.name.clone(), LabelKind::Remote));
+                    } else {
+                        entries.push((branch.name.clone(), LabelKind::Branch));
+                    }
+                }
+                for tag in &commit.tags {
+                    entries.push((tag.clone(), LabelKind::Tag));
+                }
+                if !commit.collapsed.is_empty() {
+                    let count = format!("{} commits", commit.collapsed.len());
+                    entries.push((count, LabelKind::Collapsed));
+                }
+                if entries.is_empty() {
+                    continue;
+                }
+                let sizes = entries
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (text, kind))| LabelSize {
+                        width: text_width(text, font_size) + 2.0 * padding,
+                        height: text_height(font_size) + padding,
+                        // Leave room for the arrow from HEAD to its branch
+                        gap: match kind {
+                            LabelKind::Head if index > 0 => 2.0 * padding,
+                            _ => 0.0,
+                        },
+                    })
+                    .collect::<Vec<LabelSize>>();
+                let center = positions[&commit.id];
+                let right = label_col as f64 * branch_dist;
+                // Lift the connector off the row when a connector to a parent or a child
+                // already runs along it, like the one to a merged branch
+                let runs_along = connector_lines.iter().any(|line| {
+                    line.windows(2).any(|w| {
+                        w[0].y == center.y
+                            && w[1].y == center.y
+                            && w[0].x.max(w[1].x) > center.x
+                            && w[0].x.min(w[1].x) < right
+                    })
+                });
+                let radius = radii[&commit.id];
+                let lift = if runs_along { radius / 2.0 } else { 0.0 };
+                let start =
+                    orient(center + Point::new((radius * radius - lift * lift).sqrt(), lift));
+                let anchor = orient(Point::new(right, center.y + lift));
+                // Move the labels along the history until they do not collide
+                // with labels placed before
+                let mut moved_anchor = anchor;
+                let mut rects = Layout::label_rects(moved_anchor, &sizes, options);
+                while let Some(other) = labels
+                    .iter()
+                    .map(|l| l.rect)
+                    .find(|other| rects.iter().any(|r| r.intersects(other)))
+                {
+                    let group = rects.iter().skip(1).fold(rects[0], |a, b| a.union(b));
+                    moved_anchor = match options.graph_direction {
+                        LayoutDirection::UP => {
+                            moved_anchor + Point::new(0.0, other.top() - group.y + padding)
+                        }
+                        LayoutDirection::RIGHT => {
+                            moved_anchor + Point::new(other.right() - group.x + padding, 0.0)
+                        }
+                    };
+                    rects = Layout::label_rects(moved_anchor, &sizes, options);
+                }
+                let mut connector = if moved_anchor == anchor {
+                    vec![start, anchor]
+                } else {
+                    // Bend the connector shortly before the labels
+                    let elbow = anchor.towards(start, padding);
+                    vec![start, elbow, moved_anchor]
+                };
+                let mut last_rect: Option<Rect> = None;
+                for ((text, kind), rect) in entries.into_iter().zip(rects) {
+                    let connector = match (&kind, last_rect) {
+                        (LabelKind::Head, None) => {
+                            connector.reverse();
+                            std::mem::take(&mut connector)
+                        }
+                        (LabelKind::Head, Some(last)) => Layout::head_arrow(&rect, &last),
+                        _ => std::mem::take(&mut connector),
+                    };
+                    labels.push(Label {
+                        text,
+                        commit: commit.id.clone(),
+                        kind,
+                        rect,
+                        connector,
+                    });
+                    last_rect = Some(rect);
+                }
+            }
+        }
+
+        // The brackets of the squashed commits, left of their lanes
+        let mut brackets = Vec::new();
+        let mut brackets_left: f64 = 0.0;
+        if let SquashDisplay::Expanded = options.squash_display {
+            for lane in &view.lanes {
+                for commit in lane.commits.iter().filter(|c| !c.squashed.is_empty()) {
+                    let span = commit
+                        .squashed
+                        .iter()
+                        .map(|c| {
+                            Rect::centered(positions[&c.id], 2.0 * radii[&c.id], 2.0 * radii[&c.id])
+                        })
+                        .reduce(|a, b| a.union(&b))
+                        .unwrap();
+                    let x = span.x - padding;
+                    let tick = padding;
+                    let points = vec![
+                        Point::new(x + tick, span.y),
+                        Point::new(x, span.y),
+                        Point::new(x, span.top()),
+                        Point::new(x + tick, span.top()),
+                    ];
+                    let width = text_width(&commit.id, font_size) + 2.0 * padding;
+                    let height = text_height(font_size) + padding;
+                    // The extent of the label across the lanes
+                    let across = match options.graph_direction {
+                        LayoutDirection::UP => width,
+                        LayoutDirection::RIGHT => height,
+                    };
+                    let center = Point::new(x - across / 2.0, (span.y + span.top()) / 2.0);
+                    brackets_left = brackets_left.min(x - across);
+                    brackets.push(Bracket {
+                        text: commit.id.clone(),
+                        commit: commit.id.clone(),
+                        points: points.into_iter().map(orient).collect(),
+                        label: Rect::centered(orient(center), width, height),
+                    });
+                }
+            }
+        }
+
+        // The notes, left of the lanes and the brackets
+        let mut callouts: Vec<Callout> = Vec::new();
+        let max_radius = radii.values().fold(0.0, |a: f64, &b| a.max(b));
+        let callout_x = (-max_radius).min(brackets_left) - branch_dist / 2.0;
+        // Whether a line across the lanes at `y`, from `left` to `right`,
+        // crosses a commit or a connector
+        let crosses = |y: f64, left: f64, right: f64| {
+            let crosses_commit = positions.iter().any(|(id, p)| {
+                let r = radii[*id];
+                (p.y - y).abs() <= r + padding && p.x + r > left && p.x - r < right
+            });
+            let crosses_connector = connector_lines.iter().any(|line| {
+                line.windows(2).any(|w| {
+                    let (a, b) = (w[0], w[1]);
+                    if (a.y - y) * (b.y - y) > 0.0 {
+                        return false;
+                    }
+                    if a.y == b.y {
+                        return a.x.max(b.x) >= left && a.x.min(b.x) <= right;
+                    }
+                    let x = a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y);
+                    x >= left && x <= right
+                })
+            });
+            crosses_commit || crosses_connector
+        };
+        for lane in &view.lanes {
+            for commit in &lane.commits {
+                for note in &commit.notes {
+                    let width = text_width(note, font_size) + 2.0 * padding;
+                    let height = text_height(font_size) + padding;
+                    let center = positions[&commit.id];
+                    let rect_at = |anchor: Point| match options.graph_direction {
+                        LayoutDirection::UP => {
+                            Rect::new(anchor.x - width, anchor.y - height / 2.0, width, height)
+                        }
+                        LayoutDirection::RIGHT => {
+                            Rect::new(anchor.x - width / 2.0, anchor.y, width, height)
+                        }
+                    };
+                    // The leader leaves the lane of the commit through the gap to the lane
+                    // on its left. If the lanes on the left are in the way, it runs along
+                    // the history in that gap, to the nearest place where it can pass them
+                    // and where the box does not collide with the boxes placed before.
+                    let gap_x = center.x - branch_dist / 2.0;
+                    let step = match options.graph_direction {
+                        LayoutDirection::UP => height,
+                        LayoutDirection::RIGHT => width,
+                    } + padding;
+                    let (y, rect) = (0..)
+                        .flat_map(|k| [k as f64 * step, -(k as f64) * step])
+                        .map(|offset| center.y + offset)
+                        .map(|y| (y, rect_at(orient(Point::new(callout_x, y)))))
+                        .find(|(y, rect)| {
+                            !crosses(*y, callout_x, gap_x)
+                                && !callouts.iter().any(|c| c.rect.intersects(rect))
+                        })
+                        .unwrap();
+                    let anchor = Point::new(callout_x, y);
+                    let path = if y == center.y {
+                        vec![center, anchor]
+                    } else {
+                        vec![
+                            center,
+                            Point::new(gap_x, center.y),
+                            Point::new(gap_x, y),
+                            anchor,
+                        ]
+                    };
+                    let mut leader = path.into_iter().map(orient).collect::<Vec<Point>>();
+                    leader[0] = leader[0].towards(leader[1], radii[&commit.id]);
+                    // The gap can begin right at the commit
+                    leader.dedup();
+                    callouts.push(Callout {
+                        text: note.clone(),
+                        commit: commit.id.clone(),
+                        rect,
+                        leader,
+                    });
+                }
+            }
+        }
+
+        // The boxes around the groups, one for each run of members in a lane
+        // so that the commits in between are not taken for members
+        let mut groups = Vec::new();
+        for group in &view.groups {
+            let members: HashSet<&String> = group.commits.iter().map(|c| &c.id).collect();
+            for lane in &view.lanes {
+                let mut commits = lane.commits.iter().collect::<Vec<&Rc<Commit>>>();
+                commits.sort_by_key(|c| c.row);
+                for run in commits.split(|c| !members.contains(&c.id)) {
+                    if run.is_empty() {
+                        continue;
+                    }
+                    let span = run
+                        .iter()
+                        .map(|c| {
+                            let diameter = 2.0 * radii[&c.id];
+                            Rect::centered(orient(positions[&c.id]), diameter, diameter)
+                        })
+                        .reduce(|a, b| a.union(&b))
+                        .unwrap();
+                    let label_width = text_width(&group.name, font_size) + 2.0 * padding;
+                    let label_height = text_height(font_size) + padding;
+                    let rect = |width: f64| {
+                        Rect::new(
+                            span.x - padding,
+                            span.y - padding,
+                            width,
+                            span.height + 2.0 * padding + label_height,
+                        )
+                    };
+                    let mut box_rect = rect((span.width + 2.0 * padding).max(label_width));
+                    // Only as wide as the label if that keeps the other commits out
+                    let encloses_others = nodes.iter().any(|n| {
+                        let diameter = 2.0 * n.radius;
+                        let circle = Rect::centered(n.center, diameter, diameter);
+                        !run.iter().any(|c| c.id == n.id) && box_rect.intersects(&circle)
+                    });
+                    if encloses_others {
+                        box_rect = rect(span.width + 2.0 * padding);
+                    }
+                    groups.push(GroupBox {
+                        name: group.name.clone(),
+                        rect: box_rect,
+                        label: Rect::new(
+                            box_rect.x,
+                            box_rect.top() - label_height,
+                            label_width.min(box_rect.width),
+                            label_height,
+                        ),
+                    });
+                }
+            }
+        }
+
+        let mut layout = Layout {
+            nodes,
+            edges,
+            labels,
+            brackets,
+            callouts,
+            groups,
+            swimlanes: Vec::new(),
+            legend: None,
+            titles: Vec::new(),
+            bounds: Rect::new(0.0, 0.0, 0.0, 0.0),
+        };
+        layout.update_bounds();
+        layout
+    }
+
+    // Move everything by the offset
+    pub fn translate(&mut self, offset: Point) {
+        for node in self.nodes.iter_mut() {
+            node.center = node.center + offset;
+        }
+        for edge in self.edges.iter_mut() {
+            edge.path = edge.path.map(|p| p + offset);
+            edge.points = edge.points.iter().map(|&p| p + offset).collect();
+        }
+        for label in self.labels.iter_mut() {
+            label.rect = label.rect.translated(offset);
+            label.connector = label.connector.iter().map(|&p| p + offset).collect();
+        }
+        for bracket in self.brackets.iter_mut() {
+            bracket.points = bracket.points.iter().map(|&p| p + offset).collect();
+            bracket.label = bracket.label.translated(offset);
+        }
+        for callout in self.callouts.iter_mut() {
+            callout.rect = callout.rect.translated(offset);
+            callout.leader = callout.leader.iter().map(|&p| p + offset).collect();
+        }
+        for group in self.groups.iter_mut() {
+            group.rect = group.rect.translated(offset);
+            group.label = group.label.translated(offset);
+        }
+        for swimlane in self.swimlanes.iter_mut() {
+            swimlane.rect = swimlane.rect.translated(offset);
+            swimlane.title_rect = swimlane.title_rect.translated(offset);
+        }
+        if let Some(legend) = self.legend.as_mut() {
+            legend.rect = legend.rect.translated(offset);
+            for entry in legend.entries.iter_mut() {
+                entry.sample = entry.sample.translated(offset);
+                entry.text_rect = entry.text_rect.translated(offset);
+            }
+        }
+        for title in self.titles.iter_mut() {
+            title.rect = title.rect.translated(offset);
+        }
+        self.bounds = self.bounds.translated(offset);
+    }
+
+    // Add everything of another layout to this one
+    pub fn append(&mut self, other: Layout) {
+        self.nodes.extend(other.nodes);
+        self.edges.extend(other.edges);
+        self.labels.extend(other.labels);
+        self.brackets.extend(other.brackets);
+        self.callouts.extend(other.callouts);
+        self.groups.extend(other.groups);
+        self.swimlanes.extend(other.swimlanes);
+        self.legend = self.legend.take().or(other.legend);
+        self.titles.extend(other.titles);
+        self.update_bounds();
+    }
+
+    // Rectangles of labels of the given sizes, stacked as configured.
+    // The first one starts at the anchor, away from the graph.
+    fn label_rects(anchor: Point, sizes: &[LabelSize], options: &LayoutOptions) -> Vec<Rect> {
+        let mut rects: Vec<Rect> = Vec::new();
+        for &LabelSize { width, height, gap } in sizes {
+            let rect = match (
+                rects.last(),
+                &options.graph_direction,
+                &options.label_stacking,
+            ) {
+                (None, LayoutDirection::UP, _) => {
+                    Rect::new(anchor.x, anchor.y - height / 2.0, width, height)
+                }
+                (None, LayoutDirection::RIGHT, _) => {
+                    Rect::new(anchor.x - width / 2.0, anchor.y - height, width, height)
+                }
+                (Some(last), _, LabelStacking::Horizontal) => {
+                    Rect::new(last.right() + gap, last.top() - height, width, height)
+                }
+                (Some(last), LayoutDirection::UP, LabelStacking::Vertical) => {
+                    Rect::new(last.x, last.y - gap - height, width, height)
+                }
+                (Some(last), LayoutDirection::RIGHT, LabelStacking::Vertical) => {
+                    Rect::new(anchor.x - width / 2.0, last.y - gap - height, width, height)
+                }
+            };
+            rects.push(rect);
+        }
+        rects
+    }
+
+    // Arrow from the HEAD marker to the label stacked before it
+    fn head_arrow(head: &Rect, target: &Rect) -> Vec<Point> {
+        if head.x >= target.right() {
+            let y = head.center().y;
+            vec![Point::new(head.x, y), Point::new(target.right(), y)]
+        } else {
+            // Stacked below, the arrow goes up in the middle of the overlap of both
+            let x = (head.x.max(target.x) + head.right().min(target.right())) / 2.0;
+            vec![Point::new(x, head.top()), Point::new(x, target.y)]
+        }
+    }
+
+    // Set the bounds to the smallest rectangle containing everything
+    pub fn update_bounds(&mut self) {
+        let node_rects = self
+            .nodes
+            .iter()
+            .map(|n| Rect::centered(n.center, 2.0 * n.radius, 2.0 * n.radius));
+        let edge_rects = self
+            .edges
+            .iter()
+            .flat_map(|e| e.points.iter())
+            .map(|&p| Rect::centered(p, 0.0, 0.0));
+        let label_rects = self.labels.iter().map(|l| l.rect);
+        let bracket_rects = self.brackets.iter().flat_map(|b| {
+            b.points
+                .iter()
+                .map(|&p| Rect::centered(p, 0.0, 0.0))
+                .chain([b.label])
+        });
+        let callout_rects = self.callouts.iter().map(|c| c.rect);
+        let group_rects = self.groups.iter().map(|g| g.rect);
+        let swimlane_rects = self.swimlanes.iter().map(|s| s.rect);
+        let legend_rects = self.legend.iter().map(|l| l.rect);
+        let title_rects = self.titles.iter().map(|t| t.rect);
+        self.bounds = node_rects
+            .chain(edge_rects)
+            .chain(label_rects)
+            .chain(bracket_rects)
+            .chain(callout_rects)
+            .chain(group_rects)
+            .chain(swimlane_rects)
+            .chain(legend_rects)
+            .chain(title_rects)
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0));
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Layout> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::RevisionFilter;
+    use crate::options::layout::RowMode;
+    use crate::parse_git_instructions;
+
+    fn layout(options: &LayoutOptions) -> Layout {
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            checkout(main)
+            merge(M,feature)
+            ",
+        )
+        .unwrap();
+        Layout::from_view(&View::from_state(&repo, options), options)
+    }
+
+    #[test]
+    fn positions() {
+        // Act
+        let layout = layout(&LayoutOptions::default());
+
+        // Test
+        let node = |id: &str| layout.nodes.iter().find(|n| n.id == id).unwrap().center;
+        assert_eq!(node("A"), Point::new(0.0, 0.0));
+        assert_eq!(node("B"), Point::new(2.0, 3.0));
+        assert_eq!(node("M"), Point::new(0.0, 6.0));
+        assert_eq!(layout.edges.len(), 3);
+        assert_eq!(layout.labels.len(), 3);
+        assert_eq!(layout.bounds.x, -1.0);
+        assert_eq!(layout.bounds.top(), 7.0);
+    }
+
+    #[test]
+    fn sized_to_text() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature/JIRA-1234-new-login)
+            commit(B)
+            checkout(feature/JIRA-1235-new-logout)
+            commit(C)
+            checkout(main)
+            commit(a-very-long-commit-name)
+            ",
+        )
+        .unwrap();
+        let options = LayoutOptions {
+            row_mode: RowMode::Compact,
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        let long = layout
+            .nodes
+            .iter()
+            .find(|n| n.id == "a-very-long-commit-name")
+            .unwrap();
+        assert!(2.0 * long.radius > text_width(&long.label, options.font_size));
+        for label in &layout.labels {
+            assert!(label.rect.width > text_width(&label.text, options.font_size));
+            for other in &layout.labels {
+                if other.text != label.text {
+                    assert!(!label.rect.intersects(&other.rect));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stacked_labels() {
+        // Setup
+        let mut repo = parse_git_instructions(
+            "
+            commit(A)
+            branch(release)
+            branch(backup)
+            ",
+        )
+        .unwrap();
+        repo.checkout_branch("release");
+        let options = LayoutOptions {
+            label_stacking: LabelStacking::Vertical,
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        let texts = layout
+            .labels
+            .iter()
+            .map(|l| l.text.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(texts, vec!["release", "HEAD", "main", "backup"]);
+        assert_eq!(layout.labels[0].kind, LabelKind::CheckedOut);
+        assert_eq!(layout.labels[1].kind, LabelKind::Head);
+        assert_eq!(layout.labels[2].rect.x, layout.labels[0].rect.x);
+        assert!(layout.labels[1].rect.top() < layout.labels[0].rect.y);
+        assert_eq!(
+            layout.labels[1].connector.last().unwrap().y,
+            layout.labels[0].rect.y
+        );
+        assert!(layout.labels[2].connector.is_empty());
+    }
+
+    #[test]
+    fn detached_head_marker() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            commit(B)
+            checkout(A)
+            ",
+        )
+        .unwrap();
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        let head = layout
+            .labels
+            .iter()
+            .find(|l| l.kind == LabelKind::Head)
+            .unwrap();
+        assert_eq!(head.commit, "A");
+        assert_eq!(
+            head.connector.first(),
+            Some(&Point::new(head.rect.x, head.rect.center().y))
+        );
+        assert_eq!(head.connector.last(), Some(&Point::new(1.0, 0.0)));
+    }
+
+    #[test]
+    fn elided_parents() {
+        // Setup
+        let repo = parse_git_instructions("commit(A)\ncommit(B)\ncheckout(f)\ncommit(C)").unwrap();
+        let repo = RevisionFilter::parse(&["-n", "1"])
+            .unwrap()
+            .apply(&repo)
+            .unwrap();
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        assert_eq!(layout.nodes.len(), 1);
+        assert_eq!(layout.edges.len(), 1);
+        let edge = &layout.edges[0];
+        assert_eq!((edge.from.as_str(), edge.to.as_str()), ("C", "B"));
+        assert_eq!(edge.style, STYLE_ELIDED);
+        assert_eq!(edge.points.last().unwrap().x, layout.nodes[0].center.x);
+        assert!(edge.points.last().unwrap().y < layout.nodes[0].center.y);
+    }
+
+    #[test]
+    fn collapsed_run() {
+        // Setup
+        let repo = parse_git_instructions("commit(A)\ncommit(B)\ncommit(C)\ncommit(D)").unwrap();
+        let options = LayoutOptions {
+            collapse_runs: Some(3),
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        let labels = layout
+            .nodes
+            .iter()
+            .map(|n| n.label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(labels, vec!["D", "…"]);
+        assert_eq!(layout.nodes[1].style, STYLE_COLLAPSED);
+        let count = layout
+            .labels
+            .iter()
+            .find(|l| l.kind == LabelKind::Collapsed)
+            .unwrap();
+        assert_eq!(
+            (count.text.as_str(), count.commit.as_str()),
+            ("3 commits", "C")
+        );
+    }
+
+    #[test]
+    fn tag_labels() {
+        // Setup
+        let mut repo = parse_git_instructions("commit(A)\ncommit(B)").unwrap();
+        repo.commits.get_mut("A").unwrap().tags = vec!["v1".to_string()];
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        let tag = layout
+            .labels
+            .iter()
+            .find(|l| l.kind == LabelKind::Tag)
+            .unwrap();
+        assert_eq!(tag.text, "v1");
+        assert_eq!(tag.commit, "A");
+        assert!(!tag.connector.is_empty());
+    }
+
+    #[test]
+    fn direction_right() {
+        // Setup
+        let options = LayoutOptions {
+            graph_direction: LayoutDirection::RIGHT,
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let layout = layout(&options);
+
+        // Test
+        let node = |id: &str| layout.nodes.iter().find(|n| n.id == id).unwrap().center;
+        assert_eq!(node("B"), Point::new(3.0, -2.0));
+        assert_eq!(node("M"), Point::new(6.0, 0.0));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        // Setup
+        let layout = layout(&LayoutOptions::default());
+
+        // Act
+        let json = layout.to_json().unwrap();
+        let loaded = Layout::from_json(&json).unwrap();
+
+        // Test
+        assert_eq!(loaded.nodes.len(), layout.nodes.len());
+        assert_eq!(loaded.edges[0].path, layout.edges[0].path);
+        assert_eq!(loaded.bounds, layout.bounds);
+    }
+
+    #[test]
+    fn squash_bracket() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            commit(C)
+            checkout(main)
+            merge(S, feature, squash=true)
+            ",
+        )
+        .unwrap();
+        let mut options = LayoutOptions::default();
+        let view = View::from_state(&repo, &options);
+
+        // Act
+        let collapsed = Layout::from_view(&view, &options);
+        options.squash_display = SquashDisplay::Expanded;
+        let expanded = Layout::from_view(&view, &options);
+
+        // Test
+        assert!(collapsed.brackets.is_empty());
+        assert_eq!(expanded.brackets.len(), 1);
+        let bracket = &expanded.brackets[0];
+        assert_eq!(bracket.text, "S");
+        let center = |id: &str| expanded.nodes.iter().find(|n| n.id == id).unwrap().center;
+        let (b, c) = (center("B"), center("C"));
+        assert!(bracket.points.iter().all(|p| p.x < b.x));
+        assert!(bracket.points[0].y < b.y && c.y < bracket.points[3].y);
+        assert!(bracket.label.right() <= bracket.points[1].x);
+    }
+
+    #[test]
+    fn callouts() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            note(B, \"the bug was introduced here\")
+            note(B, reported)
+            ",
+        )
+        .unwrap();
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        assert_eq!(layout.callouts.len(), 2);
+        let (first, second) = (&layout.callouts[0], &layout.callouts[1]);
+        assert_eq!(first.text, "the bug was introduced here");
+        assert_eq!(first.commit, "B");
+        // Left of all lanes, and not on top of each other
+        let lanes_left = layout
+            .nodes
+            .iter()
+            .map(|n| n.center.x - n.radius)
+            .fold(f64::MAX, f64::min);
+        assert!(layout.callouts.iter().all(|c| c.rect.right() < lanes_left));
+        assert!(!first.rect.intersects(&second.rect));
+        let b = layout.nodes.iter().find(|n| n.id == "B").unwrap().center;
+        assert_eq!(first.leader[0], b.towards(first.leader[1], 1.0));
+        assert!(layout.bounds.x <= first.rect.x);
+    }
+
+    #[test]
+    fn callout_leader_around_lanes() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            note(B, \"in between\")
+            checkout(main)
+            commit(C)
+            commit(D)
+            ",
+        )
+        .unwrap();
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        let center = |id: &str| layout.nodes.iter().find(|n| n.id == id).unwrap().center;
+        let (a, b, d) = (center("A"), center("B"), center("D"));
+        let leader = &layout.callouts[0].leader;
+        // Along the history between the lanes, then across past the end of the main lane
+        // (the circles touch, so it leaves B's circle right in the gap)
+        assert_eq!(leader.len(), 3);
+        assert!(a.x < leader[0].x && leader[0].x < b.x);
+        assert_eq!(leader[0].x, leader[1].x);
+        assert!(leader[1].y < a.y || leader[1].y > d.y);
+        assert_eq!(leader[2].y, leader[1].y);
+        assert_eq!(layout.callouts[0].rect.center().y, leader[2].y);
+    }
+
+    #[test]
+    fn label_connectors_off_edges() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            branch(release)
+            checkout(feature)
+            commit(B)
+            checkout(main)
+            merge(M, feature)
+            ",
+        )
+        .unwrap();
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        // Whether two segments lie on one line and share more than a point
+        let overlap = |(a, b): (Point, Point), (c, d): (Point, Point)| {
+            let cross = |o: Point, p: Point, q: Point| {
+                (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x)
+            };
+            if cross(a, b, c).abs() > 1e-9 || cross(a, b, d).abs() > 1e-9 {
+                return false;
+            }
+            let along = |p: Point| (p.x - a.x) * (b.x - a.x) + (p.y - a.y) * (b.y - a.y);
+            let (lo, hi) = (along(c).min(along(d)), along(c).max(along(d)));
+            hi.min(along(b)) - lo.max(along(a)) > 1e-9
+        };
+        let segments = |points: &[Point]| {
+            points
+                .windows(2)
+                .map(|w| (w[0], w[1]))
+                .collect::<Vec<(Point, Point)>>()
+        };
+        assert!(layout.labels.iter().any(|l| l.commit == "A"));
+        assert!(layout.labels.iter().any(|l| l.commit == "M"));
+        for label in &layout.labels {
+            for connector in segments(&label.connector) {
+                for edge in &layout.edges {
+                    for segment in segments(&edge.points) {
+                        assert!(
+                            !overlap(connector, segment),
+                            "{} runs along the edge {} -> {}",
+                            label.text,
+                            edge.from,
+                            edge.to
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn group_boxes() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            commit(B)
+            commit(C)
+            commit(D)
+            commit(E)
+            group(\"Sprint 12\", B, C)
+            group(\"PR #42\", E)
+            ",
+        )
+        .unwrap();
+        let options = LayoutOptions {
+            collapse_runs: Some(3),
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        assert_eq!(layout.groups.len(), 2);
+        let (sprint, pr) = (&layout.groups[0], &layout.groups[1]);
+        assert_eq!(sprint.name, "Sprint 12");
+        // B and C are in the run collapsed into D
+        let node = |id: &str| layout.nodes.iter().find(|n| n.id == id).unwrap();
+        let d = node("D");
+        assert!(
+            sprint.rect.x < d.center.x - d.radius && d.center.x + d.radius < sprint.rect.right()
+        );
+        assert!(sprint.rect.y < d.center.y - d.radius && d.center.y + d.radius < sprint.label.y);
+        assert_eq!(sprint.label.top(), sprint.rect.top());
+        assert!(sprint.label.width <= sprint.rect.width);
+        let e = node("E");
+        assert!(pr.rect.y < e.center.y && e.center.y < pr.rect.top());
+    }
+
+    #[test]
+    fn group_boxes_leave_out_commits_in_between() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(f)
+            commit(F1)
+            checkout(g)
+            commit(G1)
+            checkout(main)
+            commit(B)
+            commit(C)
+            group(S, A, G1, C)
+            ",
+        )
+        .unwrap();
+        let options = LayoutOptions::default();
+
+        // Act
+        let layout = Layout::from_view(&View::from_state(&repo, &options), &options);
+
+        // Test
+        let node = |id: &str| layout.nodes.iter().find(|n| n.id == id).unwrap();
+        let circle = |id: &str| {
+            let n = node(id);
+            Rect::centered(n.center, 2.0 * n.radius, 2.0 * n.radius)
+        };
+        // One box for A, one for C and one for G1
+        assert_eq!(layout.groups.len(), 3);
+        assert!(layout.groups.iter().all(|g| g.name == "S"));
+        for id in ["A", "G1", "C"] {
+            let c = circle(id);
+            let inside = |g: &GroupBox| {
+                g.rect.x < c.x
+                    && c.right() < g.rect.right()
+                    && g.rect.y < c.y
+                    && c.top() < g.rect.top()
+            };
+            assert_eq!(layout.groups.iter().filter(|g| inside(g)).count(), 1);
+        }
+        for id in ["F1", "B"] {
+            assert!(!layout.groups.iter().any(|g| g.rect.intersects(&circle(id))));
+        }
+    }
+}
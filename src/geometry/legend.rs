@@ -0,0 +1,288 @@
+use super::layout::{LabelKind, Layout};
+use super::point::Point;
+use super::rect::Rect;
+use super::text::{text_height, text_width};
+use crate::model::{STYLE_COLLAPSED, STYLE_ELIDED, STYLE_FADED, STYLE_HIGHLIGHT, STYLE_MOVED};
+use crate::options::layout::{Corner, LayoutOptions};
+use serde::{Deserialize, Serialize};
+
+// Sizes of titles and captions relative to the other texts, like pikchr's `big` and `small`
+const TITLE_SCALE: f64 = 1.25;
+const CAPTION_SCALE: f64 = 0.8;
+
+// What a legend entry explains, drawn like in the graph
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Symbol {
+    // A commit of the given style
+    Node(String),
+    // A connector of the given style
+    Edge(String),
+    Label(LabelKind),
+    Callout,
+    Bracket,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LegendEntry {
+    pub text: String,
+    pub symbol: Symbol,
+    // Where the symbol is drawn
+    pub sample: Rect,
+    pub text_rect: Rect,
+}
+
+// Box beside the graph explaining the symbols used in it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Legend {
+    pub rect: Rect,
+    pub entries: Vec<LegendEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TitleKind {
+    Title,
+    Subtitle,
+    Caption,
+}
+
+// Text above or below everything else
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Title {
+    pub text: String,
+    pub kind: TitleKind,
+    pub rect: Rect,
+}
+
+impl Layout {
+    // Add the legend and the titles as configured, around what has been laid out
+    pub(crate) fn add_legend_and_titles(&mut self, options: &LayoutOptions) {
+        if let Some(corner) = &options.legend {
+            self.add_legend(corner, options);
+        }
+        self.add_titles(options);
+    }
+
+    // The symbols drawn in the layout with their explanation.
+    // Branches are not colored, merges are drawn like other parents and there are no
+    // cherry-picks yet, so none of them has an entry of its own.
+    fn legend_symbols(&self) -> Vec<(Symbol, &'static str)> {
+        let mut symbols = vec![
+            (Symbol::Node("".to_string()), "commit"),
+            (
+                Symbol::Node(STYLE_HIGHLIGHT.to_string()),
+                "highlighted commit",
+            ),
+            (Symbol::Node(STYLE_FADED.to_string()), "faded commit"),
+            (
+                Symbol::Node(STYLE_COLLAPSED.to_string()),
+                "collapsed commits",
+            ),
+            (Symbol::Edge("".to_string()), "parent"),
+            (
+                Symbol::Edge(STYLE_HIGHLIGHT.to_string()),
+                "highlighted parent",
+            ),
+            (Symbol::Edge(STYLE_FADED.to_string()), "faded parent"),
+            (Symbol::Edge(STYLE_ELIDED.to_string()), "left out history"),
+            (Symbol::Edge(STYLE_MOVED.to_string()), "moved ref"),
+            (Symbol::Label(LabelKind::Branch), "branch"),
+            (Symbol::Label(LabelKind::CheckedOut), "checked out branch"),
+            (Symbol::Label(LabelKind::Head), "HEAD"),
+            (Symbol::Label(LabelKind::Remote), "remote-tracking branch"),
+            (Symbol::Label(LabelKind::Tag), "tag"),
+            (Symbol::Bracket, "squashed commits"),
+            (Symbol::Callout, "note"),
+        ];
+        symbols.retain(|(symbol, _)| match symbol {
+            Symbol::Node(style) => self.nodes.iter().any(|n| &n.style == style),
+            Symbol::Edge(style) => self.edges.iter().any(|e| &e.style == style),
+            Symbol::Label(kind) => self.labels.iter().any(|l| &l.kind == kind),
+            Symbol::Callout => !self.callouts.is_empty(),
+            Symbol::Bracket => !self.brackets.is_empty(),
+        });
+        symbols
+    }
+
+    // The legend, outside of the bounds at the given corner
+    fn add_legend(&mut self, corner: &Corner, options: &LayoutOptions) {
+        let symbols = self.legend_symbols();
+        if symbols.is_empty() {
+            return;
+        }
+        let font_size = options.font_size;
+        let padding = font_size / 2.0;
+        let line = text_height(font_size) + padding;
+        let sample_width = 2.0 * line;
+        let widest = symbols
+            .iter()
+            .map(|(_, text)| text_width(text, font_size))
+            .fold(0.0, f64::max);
+        let width = sample_width + widest + 3.0 * padding;
+        let height = symbols.len() as f64 * line + 2.0 * padding;
+        let margin = options.branch_dist as f64 / 2.0;
+        let x = match corner {
+            Corner::TopLeft | Corner::BottomLeft => self.bounds.x - margin - width,
+            Corner::TopRight | Corner::BottomRight => self.bounds.right() + margin,
+        };
+        let y = match corner {
+            Corner::TopLeft | Corner::TopRight => self.bounds.top() - height,
+            Corner::BottomLeft | Corner::BottomRight => self.bounds.y,
+        };
+        let rect = Rect::new(x, y, width, height);
+        let entries = symbols
+            .into_iter()
+            .enumerate()
+            .map(|(i, (symbol, text))| {
+                let y = rect.top() - padding - (i + 1) as f64 * line;
+                let sample = Rect::new(x + padding, y, sample_width, line);
+                let text_rect = Rect::new(
+                    sample.right() + padding,
+                    y,
+                    text_width(text, font_size),
+                    line,
+                );
+                LegendEntry {
+                    text: text.to_string(),
+                    symbol,
+                    sample,
+                    text_rect,
+                }
+            })
+            .collect();
+        self.legend = Some(Legend { rect, entries });
+        self.update_bounds();
+    }
+
+    // The title and subtitle above the bounds, the caption below
+    fn add_titles(&mut self, options: &LayoutOptions) {
+        let padding = options.font_size / 2.0;
+        let center = self.bounds.center().x;
+        let rect = |text: &str, scale: f64, y: f64| {
+            let font_size = options.font_size * scale;
+            let width = text_width(text, font_size) + 2.0 * padding;
+            let height = text_height(font_size) + padding;
+            Rect::new(center - width / 2.0, y, width, height)
+        };
+        let mut top = self.bounds.top() + padding;
+        let above = [
+            (&options.subtitle, TitleKind::Subtitle, 1.0),
+            (&options.title, TitleKind::Title, TITLE_SCALE),
+        ];
+        for (text, kind, scale) in above {
+            if let Some(text) = text {
+                let rect = rect(text, scale, top);
+                top = rect.top();
+                self.titles.push(Title {
+                    text: text.clone(),
+                    kind,
+                    rect,
+                });
+            }
+        }
+        if let Some(caption) = &options.caption {
+            let below = rect(caption, CAPTION_SCALE, 0.0);
+            let rect = below.translated(Point::new(0.0, self.bounds.y - padding - below.top()));
+            self.titles.push(Title {
+                text: caption.clone(),
+                kind: TitleKind::Caption,
+                rect,
+            });
+        }
+        self.update_bounds();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::Highlight;
+    use crate::parse_git_instructions;
+    use crate::view::View;
+
+    fn layout(options: &LayoutOptions) -> Layout {
+        let mut repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            checkout(main)
+            merge(M, feature)
+            ",
+        )
+        .unwrap();
+        repo.highlight(&Highlight::Commits(vec!["M".to_string()]))
+            .unwrap();
+        Layout::from_view(&View::from_state(&repo, options), options)
+    }
+
+    #[test]
+    fn legend_of_used_symbols() {
+        // Setup
+        let options = LayoutOptions {
+            legend: Some(Corner::TopRight),
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let layout = layout(&options);
+
+        // Test
+        let legend = layout.legend.as_ref().unwrap();
+        let texts = legend
+            .entries
+            .iter()
+            .map(|e| e.text.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            texts,
+            vec![
+                "highlighted commit",
+                "faded commit",
+                "faded parent",
+                "branch",
+                "checked out branch",
+                "HEAD"
+            ]
+        );
+        let graph_right = layout
+            .labels
+            .iter()
+            .map(|l| l.rect.right())
+            .fold(0.0, f64::max);
+        assert!(legend.rect.x > graph_right);
+        assert_eq!(legend.rect.top(), layout.bounds.top());
+        assert!(
+            legend
+                .entries
+                .iter()
+                .all(|e| e.sample.right() < e.text_rect.x
+                    && e.text_rect.right() < legend.rect.right())
+        );
+        assert!(layout.titles.is_empty());
+    }
+
+    #[test]
+    fn titles() {
+        // Setup
+        let options = LayoutOptions {
+            title: Some("Merging".to_string()),
+            subtitle: Some("with a merge commit".to_string()),
+            caption: Some("M has two parents".to_string()),
+            ..LayoutOptions::default()
+        };
+        let plain = layout(&LayoutOptions::default());
+
+        // Act
+        let layout = layout(&options);
+
+        // Test
+        assert!(layout.legend.is_none());
+        let title = |kind: TitleKind| layout.titles.iter().find(|t| t.kind == kind).unwrap();
+        let (subtitle, caption) = (title(TitleKind::Subtitle), title(TitleKind::Caption));
+        assert_eq!(title(TitleKind::Title).text, "Merging");
+        assert_eq!(title(TitleKind::Title).rect.y, subtitle.rect.top());
+        assert!(subtitle.rect.y > plain.bounds.top());
+        assert!(caption.rect.top() < plain.bounds.y);
+        assert_eq!(layout.bounds.top(), title(TitleKind::Title).rect.top());
+    }
+}
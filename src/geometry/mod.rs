@@ -0,0 +1,14 @@
+mod diagram;
+pub mod layout;
+pub mod legend;
+mod path;
+mod point;
+mod rect;
+mod routing;
+pub mod text;
+
+pub use layout::Layout;
+pub use path::{Path, Segment};
+pub use point::Point;
+pub use rect::Rect;
+pub use routing::{route_edge, EdgeKind};
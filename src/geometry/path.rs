@@ -0,0 +1,114 @@
+use super::point::Point;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Segment {
+    // Straight line to the point
+    Line(Point),
+    // Cubic Bézier curve with two control points to the last point
+    Cubic(Point, Point, Point),
+}
+
+impl Segment {
+    pub fn end(&self) -> Point {
+        match self {
+            Segment::Line(p) => *p,
+            Segment::Cubic(_, _, p) => *p,
+        }
+    }
+}
+
+// A connected sequence of segments
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Path {
+    pub start: Point,
+    pub segments: Vec<Segment>,
+}
+
+impl Path {
+    pub fn new(start: Point) -> Path {
+        Path {
+            start,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn line_to(mut self, p: Point) -> Path {
+        self.segments.push(Segment::Line(p));
+        self
+    }
+
+    pub fn cubic_to(mut self, c1: Point, c2: Point, p: Point) -> Path {
+        self.segments.push(Segment::Cubic(c1, c2, p));
+        self
+    }
+
+    pub fn end(&self) -> Point {
+        self.segments.last().map(|s| s.end()).unwrap_or(self.start)
+    }
+
+    // Shorten the path at both ends, so it starts and ends
+    // at the border of circles around the original end points.
+    // The ends are moved along the tangent of the first and last segment.
+    pub fn chop(mut self, start_radius: f64, end_radius: f64) -> Path {
+        let first_target = match self.segments.first() {
+            None => return self,
+            Some(Segment::Line(p)) => *p,
+            Some(Segment::Cubic(c1, _, _)) => *c1,
+        };
+        self.start = self.start.towards(first_target, start_radius);
+
+        let before_end = match self.segments.len() {
+            1 => self.start,
+            n => self.segments[n - 2].end(),
+        };
+        if let Some(last) = self.segments.last_mut() {
+            match last {
+                Segment::Line(p) => *p = p.towards(before_end, end_radius),
+                Segment::Cubic(_, c2, p) => *p = p.towards(*c2, end_radius),
+            }
+        }
+        self
+    }
+
+    // Apply a transformation to all points of the path,
+    // which must be affine to keep curves intact
+    pub fn map<F: Fn(Point) -> Point>(&self, f: F) -> Path {
+        Path {
+            start: f(self.start),
+            segments: self
+                .segments
+                .iter()
+                .map(|s| match s {
+                    Segment::Line(p) => Segment::Line(f(*p)),
+                    Segment::Cubic(c1, c2, p) => Segment::Cubic(f(*c1), f(*c2), f(*p)),
+                })
+                .collect(),
+        }
+    }
+
+    // Approximate the path with straight lines, for outputs that cannot draw curves.
+    // Every curve is replaced by `steps` lines.
+    pub fn flatten(&self, steps: usize) -> Vec<Point> {
+        let mut points = vec![self.start];
+        let mut current = self.start;
+        for segment in &self.segments {
+            match segment {
+                Segment::Line(p) => points.push(*p),
+                Segment::Cubic(c1, c2, p) => {
+                    for step in 1..=steps {
+                        let t = step as f64 / steps as f64;
+                        points.push(cubic_point(current, *c1, *c2, *p, t));
+                    }
+                }
+            }
+            current = segment.end();
+        }
+        points
+    }
+}
+
+fn cubic_point(p0: Point, c1: Point, c2: Point, p1: Point, t: f64) -> Point {
+    let u = 1.0 - t;
+    p0 * (u * u * u) + c1 * (3.0 * u * u * t) + c2 * (3.0 * u * t * t) + p1 * (t * t * t)
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, Mul, Sub};
+
+// A point in abstract diagram units, y grows upwards
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    pub fn length(&self) -> f64 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+
+    pub fn distance(&self, other: Point) -> f64 {
+        (other - *self).length()
+    }
+
+    // Vector of length 1 in the same direction, or the zero vector
+    pub fn normalized(&self) -> Point {
+        let length = self.length();
+        if length == 0.0 {
+            *self
+        } else {
+            *self * (1.0 / length)
+        }
+    }
+
+    // Move `distance` from this point towards `target`
+    pub fn towards(&self, target: Point, distance: f64) -> Point {
+        *self + (target - *self).normalized() * distance
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Point;
+
+    fn mul(self, factor: f64) -> Point {
+        Point::new(self.x * factor, self.y * factor)
+    }
+}
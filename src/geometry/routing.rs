@@ -0,0 +1,127 @@
+use super::path::Path;
+use super::point::Point;
+use crate::options::layout::EdgeRouting;
+
+// Length of the control point handles of a cubic curve approximating a quarter circle
+const QUARTER_CIRCLE_HANDLE: f64 = 0.5523;
+
+// Where the connector between a commit and an out of lane parent runs
+pub enum EdgeKind {
+    // The commit begins a lane, the connector runs in the column of the commit
+    BranchOff,
+    // The parent ends a lane, the connector runs in the column of the parent
+    Merge,
+    // Directly from the commit to the parent
+    Direct,
+}
+
+// The path of the connector from a commit to one of its parents,
+// running through the centers of both
+pub fn route_edge(from: Point, to: Point, kind: &EdgeKind, routing: &EdgeRouting) -> Path {
+    let corner = match kind {
+        EdgeKind::BranchOff => Some(Point::new(from.x, to.y)),
+        EdgeKind::Merge => Some(Point::new(to.x, from.y)),
+        EdgeKind::Direct => None,
+    };
+    // Without a real corner, there is nothing to route around
+    let corner = corner.filter(|&c| c != from && c != to);
+    match (routing, corner) {
+        (EdgeRouting::Bezier, _) => {
+            let middle = (from.y + to.y) / 2.0;
+            Path::new(from).cubic_to(Point::new(from.x, middle), Point::new(to.x, middle), to)
+        }
+        (EdgeRouting::Straight, _) | (_, None) => Path::new(from).line_to(to),
+        (EdgeRouting::Orthogonal, Some(corner)) => Path::new(from).line_to(corner).line_to(to),
+        (EdgeRouting::Rounded(radius), Some(corner)) => {
+            // The radius cannot be larger than the legs of the corner
+            let radius = radius.min(from.distance(corner)).min(to.distance(corner));
+            let arc_start = corner.towards(from, radius);
+            let arc_end = corner.towards(to, radius);
+            let handle = radius * QUARTER_CIRCLE_HANDLE;
+            Path::new(from)
+                .line_to(arc_start)
+                .cubic_to(
+                    arc_start.towards(corner, handle),
+                    arc_end.towards(corner, handle),
+                    arc_end,
+                )
+                .line_to(to)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::path::Segment;
+
+    #[test]
+    fn straight() {
+        // Act
+        let path = route_edge(
+            Point::new(2.0, 6.0),
+            Point::new(0.0, 0.0),
+            &EdgeKind::BranchOff,
+            &EdgeRouting::Straight,
+        );
+
+        // Test
+        assert_eq!(path.segments, vec![Segment::Line(Point::new(0.0, 0.0))]);
+    }
+
+    #[test]
+    fn orthogonal_branch_off_and_merge() {
+        // Act
+        let branch_off = route_edge(
+            Point::new(2.0, 6.0),
+            Point::new(0.0, 0.0),
+            &EdgeKind::BranchOff,
+            &EdgeRouting::Orthogonal,
+        );
+        let merge = route_edge(
+            Point::new(0.0, 9.0),
+            Point::new(2.0, 6.0),
+            &EdgeKind::Merge,
+            &EdgeRouting::Orthogonal,
+        );
+
+        // Test
+        assert_eq!(branch_off.segments[0], Segment::Line(Point::new(2.0, 0.0)));
+        assert_eq!(merge.segments[0], Segment::Line(Point::new(2.0, 9.0)));
+        assert_eq!(merge.end(), Point::new(2.0, 6.0));
+    }
+
+    #[test]
+    fn rounded_corner() {
+        // Act
+        let path = route_edge(
+            Point::new(2.0, 6.0),
+            Point::new(0.0, 0.0),
+            &EdgeKind::BranchOff,
+            &EdgeRouting::Rounded(0.5),
+        );
+
+        // Test
+        assert_eq!(path.segments.len(), 3);
+        assert_eq!(path.segments[0], Segment::Line(Point::new(2.0, 0.5)));
+        assert_eq!(path.segments[1].end(), Point::new(1.5, 0.0));
+        assert_eq!(path.end(), Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn chopped_bezier() {
+        // Act
+        let path = route_edge(
+            Point::new(2.0, 6.0),
+            Point::new(0.0, 0.0),
+            &EdgeKind::Merge,
+            &EdgeRouting::Bezier,
+        )
+        .chop(1.0, 1.0);
+
+        // Test
+        assert_eq!(path.start, Point::new(2.0, 5.0));
+        assert_eq!(path.end(), Point::new(0.0, 1.0));
+        assert_eq!(path.flatten(4).len(), 5);
+    }
+}
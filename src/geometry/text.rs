@@ -0,0 +1,51 @@
+// Text measurement with bundled font metrics, so no fonts need to be installed.
+// The metrics are the advance widths of Helvetica (from its AFM file),
+// in 1/1000 of the font size, for the printable ASCII characters.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+    278, // ' ' to '/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584,
+    556, // '0' to '?'
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722,
+    778, // '@' to 'O'
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469,
+    556, // 'P' to '_'
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556,
+    556, // '`' to 'o'
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p' to '~'
+];
+// Width used for characters without metrics, about the width of a capital letter
+const FALLBACK_WIDTH: u16 = 667;
+// Distance between the baselines of two lines, relative to the font size
+const LINE_HEIGHT: f64 = 1.2;
+
+fn char_width(c: char) -> u16 {
+    match c as usize {
+        code @ 32..=126 => HELVETICA_WIDTHS[code - 32],
+        _ => FALLBACK_WIDTH,
+    }
+}
+
+// Width of a single line of text
+pub fn text_width(text: &str, font_size: f64) -> f64 {
+    let units: u32 = text.chars().map(|c| char_width(c) as u32).sum();
+    units as f64 * font_size / 1000.0
+}
+
+// Height of a single line of text
+pub fn text_height(font_size: f64) -> f64 {
+    font_size * LINE_HEIGHT
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(text_width("", 10.0), 0.0);
+        assert_eq!(text_width("main", 1000.0), 2167.0);
+        assert!(text_width("WWW", 1.0) > text_width("iii", 1.0));
+        assert_eq!(text_width("ü", 1000.0), 667.0);
+    }
+}
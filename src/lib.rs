@@ -1,12 +1,16 @@
 #[macro_use]
 extern crate error_chain;
 
+pub mod geometry;
 pub mod model;
 pub mod options;
 mod parser;
 mod printer;
 pub mod view;
 
-pub use parser::instructions::parse_git_instructions;
-pub use printer::print_pikchr;
-
+pub use parser::instructions::{
+    errors as instruction_errors, format_script, parse_git_instructions, parse_instructions,
+    Diagnostic, DiagnosticKind, Span,
+};
+pub use parser::yaml::parse_yaml;
+pub use printer::{print_pikchr, print_pikchr_diagram, print_pikchr_diff, print_pikchr_layout};
@@ -0,0 +1,189 @@
+use git_graph_to_svg::model::{Highlight, Repository, RevisionFilter};
+use git_graph_to_svg::options::layout::{Corner, LayoutOptions};
+use git_graph_to_svg::view::View;
+use git_graph_to_svg::{format_script, parse_git_instructions, parse_yaml, print_pikchr};
+use std::io::Read;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: git-graph-to-svg <command> [options] [file...]
+
+commands:
+  pikchr [--highlight commits] [--title text] [--subtitle text] [--caption text]
+         [--legend corner] [file] [-- revision...]
+                           draw the repository as pikchr diagram, only the part
+                           selected like `main..feature --branches=release/* -n 30`,
+                           emphasizing the commits selected like the arguments of
+                           the highlight instruction, e.g. `branch=feature`,
+                           with a legend at the corner top-left, top-right,
+                           bottom-left or bottom-right
+  fmt [--check] [file...]  format instruction scripts, in place if files are given
+  script [file]            write the instructions that build the repository
+  json [--view] [file]     write the repository, or its view with the lanes, as JSON
+
+Files ending in .yaml or .yml are read as YAML, files ending in .json as JSON
+written by the json command, other files as instructions.
+Without a file, the standard input is read.";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let res = match args.first().map(|a| a.as_str()) {
+        Some("pikchr") => pikchr(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("script") => script(&args[1..]),
+        Some("json") => json(&args[1..]),
+        Some("-h" | "--help" | "help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// The content of the file, or of the standard input
+fn read_input(file: Option<&String>) -> Result<String, String> {
+    match file {
+        Some(file) => std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e)),
+        None => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| e.to_string())?;
+            Ok(input)
+        }
+    }
+}
+
+fn read_repository(file: Option<&String>) -> Result<Repository, String> {
+    let input = read_input(file)?;
+    let is_yaml = file.is_some_and(|f| f.ends_with(".yaml") || f.ends_with(".yml"));
+    if is_yaml {
+        parse_yaml(&input).map_err(|e| e.to_string())
+    } else if file.is_some_and(|f| f.ends_with(".json")) {
+        Repository::from_json(&input).map_err(|e| e.to_string())
+    } else {
+        parse_git_instructions(&input).map_err(|e| e.to_string())
+    }
+}
+
+// At most one file, and no options
+fn single_file(args: &[String]) -> Result<Option<&String>, String> {
+    match args {
+        [] => Ok(None),
+        [file] if !file.starts_with('-') => Ok(Some(file)),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn pikchr(args: &[String]) -> Result<(), String> {
+    let (args, revisions) = match args.iter().position(|a| a == "--") {
+        Some(separator) => (&args[..separator], &args[separator + 1..]),
+        None => (args, &args[args.len()..]),
+    };
+    let mut highlights = Vec::new();
+    let mut options = LayoutOptions::default();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| USAGE.to_string());
+        match arg.as_str() {
+            "--highlight" => {
+                highlights.push(Highlight::parse(&value()?).map_err(|e| e.to_string())?)
+            }
+            "--title" => options.title = Some(value()?),
+            "--subtitle" => options.subtitle = Some(value()?),
+            "--caption" => options.caption = Some(value()?),
+            "--legend" => options.legend = Some(corner(&value()?)?),
+            _ => files.push(arg.clone()),
+        }
+    }
+    let mut repo = read_repository(single_file(&files)?)?;
+    for highlight in &highlights {
+        repo.highlight(highlight).map_err(|e| e.to_string())?;
+    }
+    let repo = RevisionFilter::parse(revisions)
+        .and_then(|filter| filter.apply(&repo))
+        .map_err(|e| e.to_string())?;
+    let view = View::from_state(&repo, &options);
+    print!(
+        "{}",
+        print_pikchr(&view, &options).map_err(|e| e.to_string())?
+    );
+    Ok(())
+}
+
+fn corner(name: &str) -> Result<Corner, String> {
+    match name {
+        "top-left" => Ok(Corner::TopLeft),
+        "top-right" => Ok(Corner::TopRight),
+        "bottom-left" => Ok(Corner::BottomLeft),
+        "bottom-right" => Ok(Corner::BottomRight),
+        _ => Err(format!("unknown corner `{}`", name)),
+    }
+}
+
+fn script(args: &[String]) -> Result<(), String> {
+    let repo = read_repository(single_file(args)?)?;
+    print!("{}", repo.to_script());
+    Ok(())
+}
+
+fn json(args: &[String]) -> Result<(), String> {
+    let (view, args) = match args.first().map(|a| a.as_str()) {
+        Some("--view") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    let repo = read_repository(single_file(args)?)?;
+    let json = if view {
+        View::from_state(&repo, &LayoutOptions::default()).to_json()
+    } else {
+        repo.to_json()
+    };
+    println!("{}", json.map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+fn fmt(args: &[String]) -> Result<(), String> {
+    let check = args.iter().any(|a| a == "--check");
+    let files = args
+        .iter()
+        .filter(|a| *a != "--check")
+        .collect::<Vec<&String>>();
+    if files.iter().any(|f| f.starts_with('-')) {
+        return Err(USAGE.to_string());
+    }
+    if files.is_empty() {
+        let input = read_input(None)?;
+        let formatted = format_script(&input).map_err(|e| e.to_string())?;
+        if check && formatted != input {
+            return Err("the input is not formatted".to_string());
+        }
+        if !check {
+            print!("{}", formatted);
+        }
+        return Ok(());
+    }
+    let mut unformatted = Vec::new();
+    for file in files {
+        let input = read_input(Some(file))?;
+        let formatted = format_script(&input).map_err(|e| format!("{}:\n{}", file, e))?;
+        if formatted == input {
+            continue;
+        }
+        if check {
+            unformatted.push(file.clone());
+        } else {
+            std::fs::write(file, formatted).map_err(|e| format!("{}: {}", file, e))?;
+        }
+    }
+    if !unformatted.is_empty() {
+        return Err(format!("not formatted: {}", unformatted.join(", ")));
+    }
+    Ok(())
+}
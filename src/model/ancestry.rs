@@ -0,0 +1,249 @@
+use super::commit::Commit;
+use super::head::DETACHED_HEAD;
+use super::repo::Repository;
+use std::collections::HashSet;
+
+// How the commits between two revisions are chosen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RangeKind {
+    // `a..b`: the commits b leads to, but a does not
+    Exclusive,
+    // `a...b`: the commits one of them leads to, but not both
+    Symmetric,
+}
+
+impl Repository {
+    // The commit a revision stands for: HEAD, a branch or a commit id
+    pub fn resolve(&self, revision: &str) -> Option<&String> {
+        if revision == DETACHED_HEAD {
+            return self.current_commit();
+        }
+        self.branch_head(revision)
+            .or_else(|| self.commits.get_key_value(revision).map(|(id, _)| id))
+    }
+
+    // The commits and all their ancestors
+    pub fn ancestors<'a>(&self, ids: impl IntoIterator<Item = &'a String>) -> HashSet<String> {
+        let mut ancestors = HashSet::new();
+        let mut stack = ids.into_iter().collect::<Vec<&String>>();
+        while let Some(id) = stack.pop() {
+            if !ancestors.insert(id.clone()) {
+                continue;
+            }
+            if let Some(commit) = self.commits.get(id) {
+                stack.extend(commit.parents.iter());
+            }
+        }
+        ancestors
+    }
+
+    // Whether the descendant leads to the ancestor. A commit is its own ancestor.
+    pub fn is_ancestor(&self, ancestor: &str, descendant: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![descendant];
+        while let Some(id) = stack.pop() {
+            if id == ancestor {
+                return true;
+            }
+            if !visited.insert(id) {
+                continue;
+            }
+            if let Some(commit) = self.commits.get(id) {
+                stack.extend(commit.parents.iter().map(|p| p.as_str()));
+            }
+        }
+        false
+    }
+
+    // The best common ancestors of the revisions, the latest first:
+    // the common ancestors that are no ancestors of other common ancestors
+    pub fn merge_bases(&self, a: &str, b: &str) -> Vec<String> {
+        let (a, b) = match (self.resolve(a), self.resolve(b)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Vec::new(),
+        };
+        let common = self
+            .ancestors([a])
+            .intersection(&self.ancestors([b]))
+            .cloned()
+            .collect::<HashSet<String>>();
+        let covered = self.ancestors(common.iter().flat_map(|id| &self.commits[id].parents));
+        let mut bases = common
+            .difference(&covered)
+            .cloned()
+            .collect::<Vec<String>>();
+        bases.sort_by_key(|id| std::cmp::Reverse(self.commits[id].time));
+        bases
+    }
+
+    // The latest best common ancestor, like `git merge-base`
+    pub fn merge_base(&self, a: &str, b: &str) -> Option<String> {
+        self.merge_bases(a, b).into_iter().next()
+    }
+
+    // The commits of the range `a..b` or `a...b`, in topological order
+    pub fn commits_between(&self, a: &str, b: &str, kind: RangeKind) -> Vec<String> {
+        let from_a = self.ancestors(self.resolve(a));
+        let from_b = self.ancestors(self.resolve(b));
+        let in_range: HashSet<String> = match kind {
+            RangeKind::Exclusive => from_b.difference(&from_a).cloned().collect(),
+            RangeKind::Symmetric => from_a.symmetric_difference(&from_b).cloned().collect(),
+        };
+        self.topo_order_iter()
+            .filter(|c| in_range.contains(&c.id))
+            .map(|c| c.id.clone())
+            .collect()
+    }
+
+    // How many commits the branch has that the upstream does not have, and the other way round
+    pub fn ahead_behind(&self, branch: &str, upstream: &str) -> Option<(usize, usize)> {
+        if self.resolve(branch).is_none() || self.resolve(upstream).is_none() {
+            return None;
+        }
+        let ahead = self.commits_between(upstream, branch, RangeKind::Exclusive);
+        let behind = self.commits_between(branch, upstream, RangeKind::Exclusive);
+        Some((ahead.len(), behind.len()))
+    }
+
+    // The commits in the order they were made
+    pub fn date_order_iter(&self) -> impl Iterator<Item = &Commit> {
+        let mut commits = self.commits.values().collect::<Vec<&Commit>>();
+        commits.sort_by_key(|c| c.time);
+        commits.into_iter()
+    }
+
+    // The commits with parents before children, and the commits of a line of history
+    // together instead of mixed with the commits of other lines made at the same time
+    pub fn topo_order_iter(&self) -> impl Iterator<Item = &Commit> {
+        let mut order = Vec::with_capacity(self.commits.len());
+        let mut visited = HashSet::new();
+        // Start at the latest commits, so the longest lines are followed first
+        let mut starts = self.date_order_iter().collect::<Vec<&Commit>>();
+        starts.reverse();
+        for start in starts {
+            if !visited.insert(&start.id) {
+                continue;
+            }
+            // The commits and the number of their parents looked at
+            let mut stack = vec![(start, 0)];
+            while let Some((commit, next)) = stack.last_mut() {
+                let commit = *commit;
+                match commit.parents.get(*next) {
+                    Some(parent) => {
+                        *next += 1;
+                        if let Some(parent) = self.commits.get(parent) {
+                            if visited.insert(&parent.id) {
+                                stack.push((parent, 0));
+                            }
+                        }
+                    }
+                    None => {
+                        order.push(commit);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        order.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    fn repo() -> Repository {
+        parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            commit(C)
+            checkout(main)
+            commit(D)
+            merge(M, feature)
+            checkout(feature)
+            commit(E)
+            checkout(main)
+            commit(F)
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn ancestry() {
+        // Setup
+        let repo = repo();
+
+        // Test
+        assert!(repo.is_ancestor("B", "M"));
+        assert!(repo.is_ancestor("M", "M"));
+        assert!(!repo.is_ancestor("E", "M"));
+        assert_eq!(repo.merge_base("main", "feature"), Some("C".to_string()));
+        assert_eq!(repo.merge_base("D", "feature"), Some("A".to_string()));
+        assert_eq!(
+            repo.commits_between("feature", "main", RangeKind::Exclusive),
+            vec!["D", "M", "F"]
+        );
+        assert_eq!(
+            repo.commits_between("main", "feature", RangeKind::Symmetric),
+            vec!["D", "M", "F", "E"]
+        );
+        assert_eq!(repo.ahead_behind("feature", "main"), Some((1, 3)));
+        assert_eq!(repo.ahead_behind("feature", "unknown"), None);
+    }
+
+    #[test]
+    fn criss_cross_merge_bases() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(other)
+            commit(B)
+            checkout(main)
+            commit(C)
+            merge(M1, B)
+            checkout(other)
+            merge(M2, C)
+            ",
+        )
+        .unwrap();
+
+        // Test
+        assert_eq!(repo.merge_bases("main", "other"), vec!["C", "B"]);
+    }
+
+    #[test]
+    fn topological_order() {
+        // Setup
+        let repo = repo();
+
+        // Act
+        let order = repo
+            .topo_order_iter()
+            .map(|c| c.id.as_str())
+            .collect::<Vec<&str>>();
+
+        // Test
+        assert_eq!(order, vec!["A", "D", "B", "C", "M", "F", "E"]);
+    }
+
+    #[test]
+    fn long_history() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.add_branch("main".to_string(), None);
+        for i in 0..100_000 {
+            repo.commit(format!("C{}", i));
+        }
+
+        // Test
+        assert!(repo.is_ancestor("C0", "C99999"));
+        assert_eq!(repo.branch_commits("main").len(), 100_000);
+        assert_eq!(repo.topo_order_iter().count(), 100_000);
+        assert_eq!(repo.ahead_behind("main", "C0"), Some((99_999, 0)));
+    }
+}
@@ -1,6 +1,9 @@
+#[derive(Clone)]
 pub struct Branch {
     pub name: String,
     pub style: String,
     pub priority: usize,
     pub current_commit: Option<String>,
+    // The remote, if this is a remote-tracking branch like `origin/main`
+    pub remote: Option<String>,
 }
\ No newline at end of file
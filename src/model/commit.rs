@@ -1,7 +1,17 @@
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Commit {
     pub id: String,
     pub time: usize,
     pub branch: String,
     pub parents: Vec<String>,
+    pub message: String,
+    pub tags: Vec<String>,
+    // How the commit is drawn, e.g. faded or highlighted
+    pub style: String,
+    // The commits this one replaces, if it has been made by squashing them
+    pub squashed: Vec<String>,
+    // The parents that have been left out of the repository, see RevisionFilter
+    pub elided_parents: Vec<String>,
+    // Remarks drawn as callouts next to the commit
+    pub notes: Vec<String>,
 }
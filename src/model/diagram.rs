@@ -0,0 +1,14 @@
+use crate::model::repo::Repository;
+
+// Several named repositories drawn next to each other,
+// like an upstream repository and its fork
+#[derive(Default)]
+pub struct Diagram {
+    pub repositories: Vec<(String, Repository)>,
+}
+
+impl Diagram {
+    pub fn add_repository(&mut self, name: &str, repository: Repository) {
+        self.repositories.push((name.to_string(), repository));
+    }
+}
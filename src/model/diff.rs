@@ -0,0 +1,177 @@
+use super::diagram::Diagram;
+use super::head::{Head, DETACHED_HEAD};
+use super::repo::Repository;
+use super::style::{STYLE_FADED, STYLE_HIGHLIGHT};
+
+// A ref that points to another commit than before
+#[derive(Debug, PartialEq)]
+pub struct RefMove {
+    pub name: String,
+    // None if the ref has been created
+    pub before: Option<String>,
+    // None if the ref has been deleted
+    pub after: Option<String>,
+}
+
+// The changes between two states of a repository, e.g. before and after a rebase
+#[derive(Debug, Default)]
+pub struct RepositoryDiff {
+    pub added_commits: Vec<String>,
+    pub removed_commits: Vec<String>,
+    pub moved_refs: Vec<RefMove>,
+}
+
+impl RepositoryDiff {
+    pub fn new(before: &Repository, after: &Repository) -> RepositoryDiff {
+        let mut names = before
+            .branches
+            .keys()
+            .chain(after.branches.keys())
+            .cloned()
+            .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+        let mut moved_refs = names
+            .into_iter()
+            .map(|name| RefMove {
+                before: before.branch_head(&name).cloned(),
+                after: after.branch_head(&name).cloned(),
+                name,
+            })
+            .filter(|m| m.before != m.after)
+            .collect::<Vec<RefMove>>();
+        // HEAD only moves on its own when it is detached, otherwise it follows its branch
+        let detached = |repo: &Repository| matches!(repo.head, Some(Head::Detached(_)));
+        if (detached(before) || detached(after))
+            && before.current_commit() != after.current_commit()
+        {
+            moved_refs.push(RefMove {
+                name: DETACHED_HEAD.to_string(),
+                before: before.current_commit().cloned(),
+                after: after.current_commit().cloned(),
+            });
+        }
+
+        RepositoryDiff {
+            added_commits: RepositoryDiff::missing_commits(after, before),
+            removed_commits: RepositoryDiff::missing_commits(before, after),
+            moved_refs,
+        }
+    }
+
+    // The commits of one repository that the other one does not have, in time order
+    fn missing_commits(repo: &Repository, other: &Repository) -> Vec<String> {
+        let mut commits = repo
+            .commits
+            .values()
+            .filter(|c| !other.commits.contains_key(&c.id))
+            .collect::<Vec<_>>();
+        commits.sort_by_key(|c| c.time);
+        commits.into_iter().map(|c| c.id.clone()).collect()
+    }
+
+    // Both states as panels of a diagram, with the removed commits faded
+    // in the first and the added commits highlighted in the second
+    pub fn panels(&self, before: &Repository, after: &Repository) -> Diagram {
+        let mut before = before.clone();
+        for id in &self.removed_commits {
+            before.commits.get_mut(id).unwrap().style = STYLE_FADED.to_string();
+        }
+        let mut after = after.clone();
+        for id in &self.added_commits {
+            after.commits.get_mut(id).unwrap().style = STYLE_HIGHLIGHT.to_string();
+        }
+        let mut diagram = Diagram::default();
+        diagram.add_repository("before", before);
+        diagram.add_repository("after", after);
+        diagram
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // feature with D and E, branched off main at B
+    fn before_rebase() -> Repository {
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.commit("B".to_string());
+        repo.checkout_branch("feature");
+        repo.commit("D".to_string());
+        repo.commit("E".to_string());
+        repo.checkout_branch("main");
+        repo.commit("C".to_string());
+        repo.checkout_branch("feature");
+        repo
+    }
+
+    #[test]
+    fn rebase() {
+        // Setup
+        let before = before_rebase();
+        let mut after = Repository::default();
+        after.commit("A".to_string());
+        after.commit("B".to_string());
+        after.commit("C".to_string());
+        after.checkout_branch("feature");
+        after.commit("D'".to_string());
+        after.commit("E'".to_string());
+
+        // Act
+        let diff = RepositoryDiff::new(&before, &after);
+
+        // Test
+        assert_eq!(diff.added_commits, vec!["D'", "E'"]);
+        assert_eq!(diff.removed_commits, vec!["D", "E"]);
+        assert_eq!(
+            diff.moved_refs,
+            vec![RefMove {
+                name: "feature".to_string(),
+                before: Some("E".to_string()),
+                after: Some("E'".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn detached_head_moves() {
+        // Setup
+        let before = before_rebase();
+        let mut after = before_rebase();
+        after.checkout("B");
+
+        // Act
+        let diff = RepositoryDiff::new(&before, &after);
+
+        // Test
+        assert!(diff.added_commits.is_empty());
+        assert!(diff.removed_commits.is_empty());
+        assert_eq!(
+            diff.moved_refs,
+            vec![RefMove {
+                name: DETACHED_HEAD.to_string(),
+                before: Some("E".to_string()),
+                after: Some("B".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn panels() {
+        // Setup
+        let before = before_rebase();
+        let mut after = before_rebase();
+        after.commit("F".to_string());
+        let diff = RepositoryDiff::new(&before, &after);
+
+        // Act
+        let diagram = diff.panels(&before, &after);
+
+        // Test
+        let (name, panel) = &diagram.repositories[1];
+        assert_eq!(name, "after");
+        assert_eq!(panel.commits["F"].style, STYLE_HIGHLIGHT);
+        assert_eq!(panel.commits["E"].style, "");
+    }
+}
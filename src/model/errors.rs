@@ -0,0 +1,34 @@
+use super::json::Problem;
+
+error_chain! {
+    types {
+        Error, ErrorKind, ResultExt, Result;
+    }
+
+    foreign_links {
+        Json(::serde_json::Error);
+    }
+
+    errors {
+        InvalidRepository(problems: Vec<Problem>) {
+            description("invalid repository"),
+            display("{}", problems.iter().map(|p| p.to_string()).collect::<Vec<String>>().join("\n")),
+        }
+        UnknownRevision(revision: String) {
+            description("unknown revision"),
+            display("unknown revision `{}`", revision),
+        }
+        InvalidFilter(message: String) {
+            description("invalid revision filter"),
+            display("{}", message),
+        }
+        InvalidHighlight(message: String) {
+            description("invalid highlight"),
+            display("{}", message),
+        }
+        InvalidOperation(message: String) {
+            description("invalid operation"),
+            display("{}", message),
+        }
+    }
+}
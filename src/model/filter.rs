@@ -0,0 +1,297 @@
+use super::errors::*;
+use super::head::{Head, DETACHED_HEAD};
+use super::repo::Repository;
+use crate::options::pattern::glob_match;
+use std::collections::HashSet;
+
+// The part of a repository to draw, given like the revisions of `git log`:
+// `main..feature`, `a...b`, `^old`, `--branches=release/*`, `-n 30`, `--since-commit X`.
+// Without revisions and branch patterns, everything branches and HEAD lead to is drawn.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RevisionFilter {
+    // Revisions whose history is drawn
+    pub include: Vec<String>,
+    // Revisions whose history is left out
+    pub exclude: Vec<String>,
+    // The ranges `a...b`, the history of either but not of both
+    pub symmetric: Vec<(String, String)>,
+    // Patterns of the local branches whose history is drawn, `*` and `?` are wildcards
+    pub branch_patterns: Vec<String>,
+    // Only the latest commits
+    pub max_count: Option<usize>,
+    // Only the commits made since this one, itself included
+    pub since_commit: Option<String>,
+}
+
+// A side of a range, empty stands for HEAD like in git
+fn range_side(revision: &str) -> String {
+    if revision.is_empty() {
+        DETACHED_HEAD.to_string()
+    } else {
+        revision.to_string()
+    }
+}
+
+impl RevisionFilter {
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> Result<RevisionFilter> {
+        let mut filter = RevisionFilter::default();
+        let mut args = args.iter().map(|a| a.as_ref());
+        while let Some(arg) = args.next() {
+            let mut value = |option: &str| {
+                args.next().map(|v| v.to_string()).ok_or_else(|| {
+                    Error::from(ErrorKind::InvalidFilter(format!(
+                        "`{}` needs a value",
+                        option
+                    )))
+                })
+            };
+            if arg == "-n" || arg == "--max-count" {
+                let count = value(arg)?;
+                filter.max_count = Some(RevisionFilter::count(&count)?);
+            } else if let Some(count) = arg.strip_prefix("--max-count=") {
+                filter.max_count = Some(RevisionFilter::count(count)?);
+            } else if let Some(count) = arg.strip_prefix("-n") {
+                filter.max_count = Some(RevisionFilter::count(count)?);
+            } else if arg == "--since-commit" {
+                filter.since_commit = Some(value(arg)?);
+            } else if let Some(commit) = arg.strip_prefix("--since-commit=") {
+                filter.since_commit = Some(commit.to_string());
+            } else if arg == "--branches" {
+                filter.branch_patterns.push("*".to_string());
+            } else if let Some(pattern) = arg.strip_prefix("--branches=") {
+                filter.branch_patterns.push(pattern.to_string());
+            } else if arg.starts_with('-') {
+                bail!(ErrorKind::InvalidFilter(format!(
+                    "unknown option `{}`",
+                    arg
+                )));
+            } else if let Some(revision) = arg.strip_prefix('^') {
+                filter.exclude.push(revision.to_string());
+            } else if let Some((a, b)) = arg.split_once("...") {
+                filter.symmetric.push((range_side(a), range_side(b)));
+            } else if let Some((a, b)) = arg.split_once("..") {
+                filter.exclude.push(range_side(a));
+                filter.include.push(range_side(b));
+            } else {
+                filter.include.push(arg.to_string());
+            }
+        }
+        Ok(filter)
+    }
+
+    fn count(count: &str) -> Result<usize> {
+        count.parse().map_err(|_| {
+            ErrorKind::InvalidFilter(format!("`{}` is no number of commits", count)).into()
+        })
+    }
+
+    fn resolve<'a>(repo: &'a Repository, revision: &str) -> Result<&'a String> {
+        repo.resolve(revision)
+            .ok_or_else(|| ErrorKind::UnknownRevision(revision.to_string()).into())
+    }
+
+    // The commits to draw
+    pub fn selected(&self, repo: &Repository) -> Result<HashSet<String>> {
+        let mut starts = Vec::new();
+        let mut ends = Vec::new();
+        for revision in &self.include {
+            starts.push(RevisionFilter::resolve(repo, revision)?.clone());
+        }
+        for revision in &self.exclude {
+            ends.push(RevisionFilter::resolve(repo, revision)?.clone());
+        }
+        for (a, b) in &self.symmetric {
+            starts.push(RevisionFilter::resolve(repo, a)?.clone());
+            starts.push(RevisionFilter::resolve(repo, b)?.clone());
+            ends.extend(repo.merge_bases(a, b));
+        }
+        for branch in repo.branch_iter().filter(|b| b.remote.is_none()) {
+            if self
+                .branch_patterns
+                .iter()
+                .any(|p| glob_match(p, &branch.name))
+            {
+                starts.extend(branch.current_commit.clone());
+            }
+        }
+        if self.include.is_empty() && self.symmetric.is_empty() && self.branch_patterns.is_empty() {
+            starts.extend(repo.branch_iter().filter_map(|b| b.current_commit.clone()));
+            starts.extend(repo.current_commit().cloned());
+        }
+
+        let excluded = repo.ancestors(&ends);
+        let mut selected = repo
+            .ancestors(&starts)
+            .into_iter()
+            .filter(|id| !excluded.contains(id))
+            .collect::<HashSet<String>>();
+        if let Some(since) = &self.since_commit {
+            let since = repo.commits[RevisionFilter::resolve(repo, since)?].time;
+            selected.retain(|id| repo.commits[id].time >= since);
+        }
+        if let Some(max_count) = self.max_count {
+            let mut latest = selected.into_iter().collect::<Vec<String>>();
+            latest.sort_by_key(|id| std::cmp::Reverse(repo.commits[id].time));
+            latest.truncate(max_count);
+            selected = latest.into_iter().collect();
+        }
+        Ok(selected)
+    }
+
+    // The repository with only the selected commits. The parents that are left out
+    // are recorded as elided, branches pointing to left out commits are removed.
+    pub fn apply(&self, repo: &Repository) -> Result<Repository> {
+        let selected = self.selected(repo)?;
+        let mut res = repo.clone();
+        res.commits.retain(|id, _| selected.contains(id));
+        for commit in res.commits.values_mut() {
+            let (parents, elided_parents) = commit
+                .parents
+                .iter()
+                .cloned()
+                .partition(|id| selected.contains(id));
+            commit.parents = parents;
+            commit.elided_parents = elided_parents;
+            commit.squashed.retain(|id| selected.contains(id));
+        }
+        res.branches.retain(|_, b| {
+            b.current_commit
+                .as_ref()
+                .is_some_and(|id| selected.contains(id))
+        });
+        for group in res.groups.iter_mut() {
+            group.commits.retain(|id| selected.contains(id));
+        }
+        res.groups.retain(|g| !g.commits.is_empty());
+        res.head = match &repo.head {
+            Some(Head::Branch(name)) if !res.branches.contains_key(name) => None,
+            Some(Head::Detached(id)) if !selected.contains(id) => None,
+            head => head.clone(),
+        };
+        let ordered = repo
+            .date_order_iter()
+            .filter(|c| selected.contains(&c.id))
+            .map(|c| c.id.clone())
+            .collect::<Vec<String>>();
+        for (time, id) in ordered.iter().enumerate() {
+            res.commits.get_mut(id).unwrap().time = time;
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    fn repo() -> Repository {
+        parse_git_instructions(
+            "
+            commit(A)
+            commit(B)
+            checkout(release/1)
+            commit(R1)
+            checkout(main)
+            commit(C)
+            checkout(feature)
+            commit(F1)
+            commit(F2)
+            checkout(main)
+            commit(D)
+            merge(M, feature)
+            ",
+        )
+        .unwrap()
+    }
+
+    fn ids(repo: &Repository) -> Vec<String> {
+        repo.date_order_iter().map(|c| c.id.clone()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        // Act
+        let filter = RevisionFilter::parse(&[
+            "main..feature",
+            "a...b",
+            "^old",
+            "--branches=release/*",
+            "-n",
+            "30",
+            "--since-commit",
+            "X",
+        ])
+        .unwrap();
+
+        // Test
+        assert_eq!(filter.include, vec!["feature"]);
+        assert_eq!(filter.exclude, vec!["main", "old"]);
+        assert_eq!(filter.symmetric, vec![("a".to_string(), "b".to_string())]);
+        assert_eq!(filter.branch_patterns, vec!["release/*"]);
+        assert_eq!(filter.max_count, Some(30));
+        assert_eq!(filter.since_commit, Some("X".to_string()));
+        assert!(RevisionFilter::parse(&["--oneline"]).is_err());
+        assert!(RevisionFilter::parse(&["-n", "many"]).is_err());
+    }
+
+    #[test]
+    fn range() {
+        // Setup
+        let filter = RevisionFilter::parse(&["C..feature"]).unwrap();
+
+        // Act
+        let res = filter.apply(&repo()).unwrap();
+
+        // Test
+        assert_eq!(ids(&res), vec!["F1", "F2"]);
+        assert!(res.commits["F1"].parents.is_empty());
+        assert_eq!(res.commits["F1"].elided_parents, vec!["C"]);
+        assert_eq!(
+            res.branches.keys().collect::<Vec<&String>>(),
+            vec!["feature"]
+        );
+        assert_eq!(res.head, None);
+    }
+
+    #[test]
+    fn cut_history() {
+        // Setup
+        let repo = repo();
+
+        // Act
+        let release = RevisionFilter::parse(&["--branches=release/*", "--since-commit=B"])
+            .unwrap()
+            .apply(&repo)
+            .unwrap();
+        let latest = RevisionFilter::parse(&["-n", "2"])
+            .unwrap()
+            .apply(&repo)
+            .unwrap();
+        let symmetric = RevisionFilter::parse(&["release/1...feature"])
+            .unwrap()
+            .apply(&repo)
+            .unwrap();
+
+        // Test
+        assert_eq!(ids(&release), vec!["B", "R1"]);
+        assert_eq!(release.commits["B"].elided_parents, vec!["A"]);
+        assert_eq!(release.commits["B"].time, 0);
+        assert_eq!(ids(&latest), vec!["D", "M"]);
+        assert_eq!(latest.commits["M"].parents, vec!["D"]);
+        assert_eq!(latest.commits["M"].elided_parents, vec!["F2"]);
+        assert_eq!(latest.head, Some(Head::Branch("main".to_string())));
+        assert_eq!(ids(&symmetric), vec!["R1", "C", "F1", "F2"]);
+    }
+
+    #[test]
+    fn unknown_revision() {
+        // Act
+        let res = RevisionFilter::parse(&["main..nope"])
+            .unwrap()
+            .apply(&repo());
+
+        // Test
+        assert_eq!(res.err().unwrap().to_string(), "unknown revision `nope`");
+    }
+}
@@ -0,0 +1,88 @@
+use super::errors::*;
+use super::highlight::Highlight;
+use super::repo::Repository;
+use serde::{Deserialize, Serialize};
+
+// Commits drawn in a labeled box, like the ones of a sprint or a pull request
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Group {
+    pub name: String,
+    // In the order they were made
+    pub commits: Vec<String>,
+}
+
+impl Repository {
+    // Group the commits selected like for highlighting, as they are now.
+    // Nothing is grouped if no commit is selected.
+    pub fn group(&mut self, name: &str, selection: &Highlight) -> Result<()> {
+        let mut commits = self
+            .highlighted_commits(selection)?
+            .into_iter()
+            .collect::<Vec<String>>();
+        if commits.is_empty() {
+            return Ok(());
+        }
+        commits.sort_by_key(|id| self.commits[id].time);
+        self.groups.push(Group {
+            name: name.to_string(),
+            commits,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    #[test]
+    fn groups() {
+        // Act
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            commit(C)
+            group(\"PR #42\", range=main..feature)
+            checkout(main)
+            commit(D)
+            group(\"Sprint 12\", D, A)
+            group(Empty, range=feature..A)
+            ",
+        )
+        .unwrap();
+
+        // Test
+        assert_eq!(
+            repo.groups,
+            vec![
+                Group {
+                    name: "PR #42".to_string(),
+                    commits: vec!["B".to_string(), "C".to_string()],
+                },
+                Group {
+                    name: "Sprint 12".to_string(),
+                    commits: vec!["A".to_string(), "D".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_commits() {
+        // Setup
+        let mut repo = parse_git_instructions("commit(A)").unwrap();
+
+        // Act
+        let res = repo.group(
+            "Sprint 12",
+            &Highlight::Commits(vec!["A".into(), "nope".into()]),
+        );
+
+        // Test
+        assert_eq!(res.unwrap_err().to_string(), "unknown revision `nope`");
+        assert!(repo.groups.is_empty());
+    }
+}
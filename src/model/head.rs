@@ -0,0 +1,11 @@
+// Name of the branch that commits made on a detached HEAD belong to
+pub const DETACHED_HEAD: &str = "HEAD";
+
+// What is checked out
+#[derive(Clone, Debug, PartialEq)]
+pub enum Head {
+    // A branch, new commits move the branch
+    Branch(String),
+    // A commit directly, new commits belong to no branch
+    Detached(String),
+}
@@ -0,0 +1,223 @@
+use super::errors::*;
+use super::filter::RevisionFilter;
+use super::repo::Repository;
+use super::style::{STYLE_FADED, STYLE_HIGHLIGHT};
+use crate::parser::instructions::{parse_instructions, Instruction};
+use std::collections::HashSet;
+
+// Commits to emphasize, see Repository::highlight
+#[derive(Clone, Debug, PartialEq)]
+pub enum Highlight {
+    // The commits given directly, or by a branch or HEAD pointing to them
+    Commits(Vec<String>),
+    // All commits of a branch, see Repository::branch_commits
+    Branch(String),
+    // The commits of a revision range like `main..feature`, see RevisionFilter
+    Range(String),
+    // The commits on the paths from a commit to one of its descendants
+    Path(String, String),
+}
+
+impl Highlight {
+    // The highlight of the arguments of a `highlight(...)` instruction, like `branch=feature`
+    pub fn parse(args: &str) -> Result<Highlight> {
+        match parse_instructions(&format!("highlight({})", args)) {
+            Ok(instructions) => match instructions.as_slice() {
+                [Instruction::HIGHLIGHT(highlight)] => Ok(highlight.clone()),
+                _ => bail!(ErrorKind::InvalidHighlight(format!(
+                    "`{}` is no highlight",
+                    args
+                ))),
+            },
+            Err(diagnostics) => bail!(ErrorKind::InvalidHighlight(
+                diagnostics
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            )),
+        }
+    }
+}
+
+impl Repository {
+    // The commits of a highlight, which fails for revisions that do not exist
+    pub fn highlighted_commits(&self, highlight: &Highlight) -> Result<HashSet<String>> {
+        let resolve = |revision: &str| {
+            self.resolve(revision).ok_or_else(|| {
+                Error::from(ErrorKind::InvalidHighlight(format!(
+                    "unknown revision `{}`",
+                    revision
+                )))
+            })
+        };
+        match highlight {
+            Highlight::Commits(revisions) => {
+                revisions.iter().map(|r| resolve(r).cloned()).collect()
+            }
+            Highlight::Branch(branch) => {
+                if !self.branches.contains_key(branch) {
+                    bail!(ErrorKind::InvalidHighlight(format!(
+                        "unknown branch `{}`",
+                        branch
+                    )));
+                }
+                Ok(self.branch_commits(branch))
+            }
+            Highlight::Range(range) => RevisionFilter::parse(&[range])
+                .and_then(|filter| filter.selected(self))
+                .map_err(|e| ErrorKind::InvalidHighlight(e.to_string()).into()),
+            Highlight::Path(from, to) => {
+                let from = resolve(from)?;
+                // The ancestors of `to` that have `from` as ancestor
+                let ancestors = self.ancestors(Some(resolve(to)?));
+                let mut descendants = HashSet::from([from.clone()]);
+                for commit in self.date_order_iter() {
+                    if commit.parents.iter().any(|p| descendants.contains(p)) {
+                        descendants.insert(commit.id.clone());
+                    }
+                }
+                Ok(ancestors.intersection(&descendants).cloned().collect())
+            }
+        }
+    }
+
+    // Emphasize the commits, in addition to the ones highlighted before, and fade the others.
+    // The highlight is resolved against the repository as it is now, commits made later
+    // are left alone. Nothing changes if the highlight does not fit the repository.
+    pub fn highlight(&mut self, highlight: &Highlight) -> Result<()> {
+        let selected = self.highlighted_commits(highlight)?;
+        for commit in self.commits.values_mut() {
+            if selected.contains(&commit.id) {
+                commit.style = STYLE_HIGHLIGHT.to_string();
+            } else if commit.style != STYLE_HIGHLIGHT {
+                commit.style = STYLE_FADED.to_string();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    fn highlighted(highlight: Highlight) -> Result<Vec<String>> {
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            commit(C)
+            checkout(main)
+            commit(D)
+            merge(M, feature)
+            commit(E)
+            ",
+        )
+        .unwrap();
+        let mut ids = repo
+            .highlighted_commits(&highlight)?
+            .into_iter()
+            .collect::<Vec<String>>();
+        ids.sort();
+        Ok(ids)
+    }
+
+    #[test]
+    fn selections() {
+        assert_eq!(
+            highlighted(Highlight::Commits(vec!["A".into(), "feature".into()])).unwrap(),
+            vec!["A", "C"]
+        );
+        assert_eq!(
+            highlighted(Highlight::Branch("feature".into())).unwrap(),
+            vec!["A", "B", "C"]
+        );
+        assert_eq!(
+            highlighted(Highlight::Range("feature..main".into())).unwrap(),
+            vec!["D", "E", "M"]
+        );
+        assert_eq!(
+            highlighted(Highlight::Path("B".into(), "E".into())).unwrap(),
+            vec!["B", "C", "E", "M"]
+        );
+        assert!(highlighted(Highlight::Path("D".into(), "C".into()))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn unknown_revisions() {
+        let message = |highlight| highlighted(highlight).unwrap_err().to_string();
+        assert_eq!(
+            message(Highlight::Commits(vec!["A".into(), "nope".into()])),
+            "unknown revision `nope`"
+        );
+        assert_eq!(
+            message(Highlight::Branch("nope".into())),
+            "unknown branch `nope`"
+        );
+        assert_eq!(
+            message(Highlight::Range("main..nope".into())),
+            "unknown revision `nope`"
+        );
+        assert_eq!(
+            message(Highlight::Path("A".into(), "nope".into())),
+            "unknown revision `nope`"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Highlight::parse("from=A, to=M").unwrap(),
+            Highlight::Path("A".to_string(), "M".to_string())
+        );
+        assert!(Highlight::parse("").is_err());
+        assert!(Highlight::parse("A) commit(B").is_err());
+    }
+
+    #[test]
+    fn styles() {
+        // Setup
+        let mut repo = parse_git_instructions("commit(A)\ncommit(B)\ncommit(C)").unwrap();
+
+        // Act
+        repo.highlight(&Highlight::Commits(vec!["B".into()]))
+            .unwrap();
+        repo.highlight(&Highlight::Commits(vec!["C".into()]))
+            .unwrap();
+        let unknown = repo.highlight(&Highlight::Commits(vec!["nope".into()]));
+
+        // Test
+        assert!(unknown.is_err());
+        assert_eq!(repo.commits["A"].style, STYLE_FADED);
+        assert_eq!(repo.commits["B"].style, STYLE_HIGHLIGHT);
+        assert_eq!(repo.commits["C"].style, STYLE_HIGHLIGHT);
+    }
+
+    #[test]
+    fn commits_after_highlight() {
+        // Act
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            highlight(branch=feature)
+            commit(C)
+            checkout(main)
+            commit(D)
+            ",
+        )
+        .unwrap();
+
+        // Test
+        assert_eq!(repo.commits["A"].style, STYLE_HIGHLIGHT);
+        assert_eq!(repo.commits["B"].style, STYLE_HIGHLIGHT);
+        assert_eq!(repo.commits["C"].style, STYLE_HIGHLIGHT);
+        assert_eq!(repo.commits["D"].style, STYLE_FADED);
+    }
+}
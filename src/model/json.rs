@@ -0,0 +1,585 @@
+use super::branch::Branch;
+use super::commit::Commit;
+use super::errors::*;
+use super::group::Group;
+use super::head::Head;
+use super::repo::Repository;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+
+// The JSON form of a repository. Commits are written in the order they were made.
+// When reading, they may be listed in any order, the commits are ordered
+// after their parents and the commits they replace.
+#[derive(Serialize, Deserialize)]
+struct RepositoryJson {
+    commits: Vec<CommitJson>,
+    branches: Vec<BranchJson>,
+    #[serde(default)]
+    head: Option<HeadJson>,
+    #[serde(default)]
+    remotes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<Group>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CommitJson {
+    id: String,
+    branch: String,
+    #[serde(default)]
+    parents: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    style: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    squashed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    elided_parents: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BranchJson {
+    name: String,
+    #[serde(default)]
+    commit: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    style: String,
+    #[serde(default)]
+    priority: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+}
+
+// `{"branch": "main"}` or `{"detached": "C1"}`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HeadJson {
+    Branch(String),
+    Detached(String),
+}
+
+// Something wrong in a repository, at the place of the JSON document it is in
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    // Like `commits[3].parents[1]`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl From<&Head> for HeadJson {
+    fn from(head: &Head) -> HeadJson {
+        match head {
+            Head::Branch(name) => HeadJson::Branch(name.clone()),
+            Head::Detached(id) => HeadJson::Detached(id.clone()),
+        }
+    }
+}
+
+impl From<HeadJson> for Head {
+    fn from(head: HeadJson) -> Head {
+        match head {
+            HeadJson::Branch(name) => Head::Branch(name),
+            HeadJson::Detached(id) => Head::Detached(id),
+        }
+    }
+}
+
+impl From<&Repository> for RepositoryJson {
+    fn from(repo: &Repository) -> RepositoryJson {
+        let mut commits = repo.commits.values().collect::<Vec<&Commit>>();
+        commits.sort_by_key(|c| c.time);
+        let commits = commits
+            .into_iter()
+            .map(|c| CommitJson {
+                id: c.id.clone(),
+                branch: c.branch.clone(),
+                parents: c.parents.clone(),
+                message: c.message.clone(),
+                tags: c.tags.clone(),
+                style: c.style.clone(),
+                squashed: c.squashed.clone(),
+                elided_parents: c.elided_parents.clone(),
+                notes: c.notes.clone(),
+            })
+            .collect();
+        let mut branches = repo.branches.values().collect::<Vec<&Branch>>();
+        branches.sort_by(|a, b| (a.priority, &a.name).cmp(&(b.priority, &b.name)));
+        let branches = branches
+            .into_iter()
+            .map(|b| BranchJson {
+                name: b.name.clone(),
+                commit: b.current_commit.clone(),
+                style: b.style.clone(),
+                priority: b.priority,
+                remote: b.remote.clone(),
+            })
+            .collect();
+        RepositoryJson {
+            commits,
+            branches,
+            head: repo.head.as_ref().map(HeadJson::from),
+            remotes: repo.remotes.clone(),
+            groups: repo.groups.clone(),
+        }
+    }
+}
+
+impl RepositoryJson {
+    fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut problem = |path: String, message: String| problems.push(Problem { path, message });
+
+        let mut positions: HashMap<&String, usize> = HashMap::new();
+        for (i, commit) in self.commits.iter().enumerate() {
+            if commit.id.is_empty() {
+                problem(format!("commits[{}].id", i), "the id is empty".to_string());
+            } else if let Some(first) = positions.get(&commit.id) {
+                problem(
+                    format!("commits[{}].id", i),
+                    format!(
+                        "commit `{}` is already listed at commits[{}]",
+                        commit.id, first
+                    ),
+                );
+            } else {
+                positions.insert(&commit.id, i);
+            }
+        }
+        for (i, commit) in self.commits.iter().enumerate() {
+            for (j, parent) in commit.parents.iter().enumerate() {
+                if !positions.contains_key(parent) {
+                    problem(
+                        format!("commits[{}].parents[{}]", i, j),
+                        format!("unknown commit `{}`", parent),
+                    );
+                }
+            }
+            for (j, squashed) in commit.squashed.iter().enumerate() {
+                if !positions.contains_key(squashed) {
+                    problem(
+                        format!("commits[{}].squashed[{}]", i, j),
+                        format!("unknown commit `{}`", squashed),
+                    );
+                }
+            }
+        }
+
+        let mut names: HashMap<&String, usize> = HashMap::new();
+        for (i, branch) in self.branches.iter().enumerate() {
+            if branch.name.is_empty() {
+                problem(
+                    format!("branches[{}].name", i),
+                    "the name is empty".to_string(),
+                );
+            } else if let Some(first) = names.get(&branch.name) {
+                problem(
+                    format!("branches[{}].name", i),
+                    format!(
+                        "branch `{}` is already listed at branches[{}]",
+                        branch.name, first
+                    ),
+                );
+            } else {
+                names.insert(&branch.name, i);
+            }
+            if let Some(commit) = &branch.commit {
+                if !positions.contains_key(commit) {
+                    problem(
+                        format!("branches[{}].commit", i),
+                        format!("unknown commit `{}`", commit),
+                    );
+                }
+            }
+            if let Some(remote) = &branch.remote {
+                if !self.remotes.contains(remote) {
+                    problem(
+                        format!("branches[{}].remote", i),
+                        format!("unknown remote `{}`", remote),
+                    );
+                } else if !branch.name.starts_with(&format!("{}/", remote)) {
+                    problem(
+                        format!("branches[{}].name", i),
+                        format!(
+                            "the name of a branch of `{}` starts with `{}/`",
+                            remote, remote
+                        ),
+                    );
+                }
+            }
+        }
+
+        match &self.head {
+            Some(HeadJson::Branch(name)) if !names.contains_key(name) => problem(
+                "head.branch".to_string(),
+                format!("unknown branch `{}`", name),
+            ),
+            Some(HeadJson::Detached(id)) if !positions.contains_key(id) => problem(
+                "head.detached".to_string(),
+                format!("unknown commit `{}`", id),
+            ),
+            _ => {}
+        }
+
+        for (i, group) in self.groups.iter().enumerate() {
+            for (j, commit) in group.commits.iter().enumerate() {
+                if !positions.contains_key(commit) {
+                    problem(
+                        format!("groups[{}].commits[{}]", i, j),
+                        format!("unknown commit `{}`", commit),
+                    );
+                }
+            }
+        }
+
+        // A cycle can only be found once all commits are known
+        if problems.is_empty() {
+            if let Some((i, path)) = self.cycle(&positions) {
+                problems.push(Problem {
+                    path,
+                    message: format!("commit `{}` is its own ancestor", self.commits[i].id),
+                });
+            }
+        }
+        problems
+    }
+
+    // The positions of the commits a commit comes after, with the paths they are listed at
+    fn earlier<'a>(
+        &'a self,
+        i: usize,
+        positions: &'a HashMap<&String, usize>,
+    ) -> impl Iterator<Item = (usize, String)> + 'a {
+        let commit = &self.commits[i];
+        let parents = commit
+            .parents
+            .iter()
+            .enumerate()
+            .map(move |(j, id)| (positions[id], format!("commits[{}].parents[{}]", i, j)));
+        let squashed = commit
+            .squashed
+            .iter()
+            .enumerate()
+            .map(move |(j, id)| (positions[id], format!("commits[{}].squashed[{}]", i, j)));
+        parents.chain(squashed)
+    }
+
+    // A commit in a cycle, and where the cycle is closed
+    fn cycle(&self, positions: &HashMap<&String, usize>) -> Option<(usize, String)> {
+        // 0: not visited, 1: being visited, 2: done
+        let mut state = vec![0; self.commits.len()];
+        for start in 0..self.commits.len() {
+            if state[start] != 0 {
+                continue;
+            }
+            let mut stack = vec![(start, self.earlier(start, positions).collect::<Vec<_>>())];
+            state[start] = 1;
+            while let Some((i, next)) = stack.last_mut() {
+                match next.pop() {
+                    Some((j, path)) if state[j] == 1 => return Some((j, path)),
+                    Some((j, _)) if state[j] == 0 => {
+                        state[j] = 1;
+                        stack.push((j, self.earlier(j, positions).collect()));
+                    }
+                    Some(_) => {}
+                    None => {
+                        state[*i] = 2;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // The times of the commits: after the commits they come after,
+    // otherwise in the order they are listed
+    fn times(&self, positions: &HashMap<&String, usize>) -> Vec<usize> {
+        let mut later = vec![Vec::new(); self.commits.len()];
+        let mut waiting_for = (0..self.commits.len())
+            .map(|i| {
+                self.earlier(i, positions)
+                    .map(|(j, _)| later[j].push(i))
+                    .count()
+            })
+            .collect::<Vec<usize>>();
+        let mut ready = (0..self.commits.len())
+            .filter(|i| waiting_for[*i] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<Reverse<usize>>>();
+        let mut times = vec![0; self.commits.len()];
+        let mut time = 0;
+        while let Some(Reverse(i)) = ready.pop() {
+            times[i] = time;
+            time += 1;
+            for &j in &later[i] {
+                waiting_for[j] -= 1;
+                if waiting_for[j] == 0 {
+                    ready.push(Reverse(j));
+                }
+            }
+        }
+        times
+    }
+}
+
+impl TryFrom<RepositoryJson> for Repository {
+    type Error = Error;
+
+    fn try_from(json: RepositoryJson) -> Result<Repository> {
+        let problems = json.validate();
+        if !problems.is_empty() {
+            bail!(ErrorKind::InvalidRepository(problems));
+        }
+        let positions = json
+            .commits
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (&c.id, i))
+            .collect::<HashMap<&String, usize>>();
+        let times = json.times(&positions);
+        let commits = json
+            .commits
+            .into_iter()
+            .zip(times)
+            .map(|(c, time)| {
+                let commit = Commit {
+                    id: c.id,
+                    time,
+                    branch: c.branch,
+                    parents: c.parents,
+                    message: c.message,
+                    tags: c.tags,
+                    style: c.style,
+                    squashed: c.squashed,
+                    elided_parents: c.elided_parents,
+                    notes: c.notes,
+                };
+                (commit.id.clone(), commit)
+            })
+            .collect();
+        let branches = json
+            .branches
+            .into_iter()
+            .map(|b| {
+                let branch = Branch {
+                    name: b.name,
+                    style: b.style,
+                    priority: b.priority,
+                    current_commit: b.commit,
+                    remote: b.remote,
+                };
+                (branch.name.clone(), branch)
+            })
+            .collect();
+        Ok(Repository {
+            branches,
+            head: json.head.map(Head::from),
+            commits,
+            remotes: json.remotes,
+            groups: json.groups,
+        })
+    }
+}
+
+impl Serialize for Repository {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        RepositoryJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Repository {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Repository, D::Error> {
+        Repository::try_from(RepositoryJson::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Repository {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    // Read a repository from JSON, as written by to_json or by hand
+    pub fn from_json(json: &str) -> Result<Repository> {
+        Repository::try_from(serde_json::from_str::<RepositoryJson>(json)?)
+    }
+
+    // Check that the commits and refs the repository refers to exist and that
+    // the history has no cycles. The problems are reported at their place in the JSON form.
+    pub fn validate(&self) -> Result<()> {
+        let problems = RepositoryJson::from(self).validate();
+        if !problems.is_empty() {
+            bail!(ErrorKind::InvalidRepository(problems));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    #[test]
+    fn round_trip() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            push(origin)
+            checkout(feature)
+            commit(B)
+            checkout(main)
+            merge(M, feature, squash=true)
+            checkout(B)
+            group(\"PR #42\", A, B)
+            ",
+        )
+        .unwrap();
+
+        // Act
+        let json = repo.to_json().unwrap();
+        let loaded = Repository::from_json(&json).unwrap();
+
+        // Test
+        assert_eq!(loaded.to_json().unwrap(), json);
+        assert_eq!(loaded.commits["M"].time, repo.commits["M"].time);
+        assert_eq!(loaded.commits["M"].squashed, vec!["B".to_string()]);
+        assert_eq!(loaded.head, Some(Head::Detached("B".to_string())));
+        assert_eq!(loaded.groups, repo.groups);
+        assert_eq!(
+            loaded.branches["origin/main"].remote,
+            Some("origin".to_string())
+        );
+    }
+
+    #[test]
+    fn schema() {
+        // Setup
+        let repo = parse_git_instructions("commit(A)\ncheckout(feature)\ncommit(B)").unwrap();
+
+        // Act
+        let json = serde_json::to_value(&repo).unwrap();
+
+        // Test
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "commits": [
+                    {"id": "A", "branch": "main", "parents": []},
+                    {"id": "B", "branch": "feature", "parents": ["A"]},
+                ],
+                "branches": [
+                    {"name": "main", "commit": "A", "priority": 0},
+                    {"name": "feature", "commit": "B", "priority": 1},
+                ],
+                "head": {"branch": "feature"},
+                "remotes": [],
+            })
+        );
+    }
+
+    fn problems(json: &str) -> Vec<String> {
+        match Repository::from_json(json) {
+            Err(Error(ErrorKind::InvalidRepository(problems), _)) => {
+                problems.iter().map(|p| p.to_string()).collect()
+            }
+            _ => panic!("expected an invalid repository"),
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        // Act
+        let problems = problems(
+            r#"{
+                "commits": [
+                    {"id": "A", "branch": "main"},
+                    {"id": "B", "branch": "main", "parents": ["A"]},
+                    {"id": "A", "branch": "main"},
+                    {"id": "C", "branch": "main", "parents": ["B", "X"]}
+                ],
+                "branches": [
+                    {"name": "main", "commit": "Y"},
+                    {"name": "origin/main", "commit": "A", "remote": "origin"}
+                ],
+                "head": {"branch": "feature"},
+                "groups": [{"name": "PR #42", "commits": ["B", "Z"]}]
+            }"#,
+        );
+
+        // Test
+        assert_eq!(
+            problems,
+            vec![
+                "commits[2].id: commit `A` is already listed at commits[0]",
+                "commits[3].parents[1]: unknown commit `X`",
+                "branches[0].commit: unknown commit `Y`",
+                "branches[1].remote: unknown remote `origin`",
+                "head.branch: unknown branch `feature`",
+                "groups[0].commits[1]: unknown commit `Z`",
+            ]
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        // Act
+        let problems = problems(
+            r#"{
+                "commits": [
+                    {"id": "A", "branch": "main", "parents": ["C"]},
+                    {"id": "B", "branch": "main", "parents": ["A"]},
+                    {"id": "C", "branch": "main", "parents": ["B"]}
+                ],
+                "branches": []
+            }"#,
+        );
+
+        // Test
+        assert_eq!(
+            problems,
+            vec!["commits[1].parents[0]: commit `A` is its own ancestor"]
+        );
+    }
+
+    #[test]
+    fn unordered_commits() {
+        // Act
+        let repo = Repository::from_json(
+            r#"{
+                "commits": [
+                    {"id": "M", "branch": "main", "parents": ["A", "B"], "message": "Merge"},
+                    {"id": "B", "branch": "feature", "parents": ["A"], "tags": ["v1"]},
+                    {"id": "A", "branch": "main"}
+                ],
+                "branches": [{"name": "main", "commit": "M"}],
+                "head": {"branch": "main"}
+            }"#,
+        )
+        .unwrap();
+
+        // Test
+        assert_eq!(repo.commits["A"].time, 0);
+        assert_eq!(repo.commits["B"].time, 1);
+        assert_eq!(repo.commits["M"].time, 2);
+        assert_eq!(repo.commits["M"].message, "Merge");
+        assert_eq!(repo.commits["B"].tags, vec!["v1".to_string()]);
+        assert!(repo.validate().is_ok());
+    }
+}
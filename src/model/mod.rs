@@ -1,6 +1,28 @@
 pub mod repo;
+mod ancestry;
 mod branch;
 mod commit;
+mod diagram;
+mod diff;
+pub mod errors;
+mod filter;
+mod group;
+mod head;
+mod highlight;
+pub(crate) mod json;
+mod script;
+#[allow(dead_code)]
 mod style;
 
+pub use ancestry::RangeKind;
+pub use branch::Branch;
+pub use commit::Commit;
+pub use diagram::Diagram;
+pub use diff::{RefMove, RepositoryDiff};
+pub use filter::RevisionFilter;
+pub use group::Group;
+pub use head::{Head, DETACHED_HEAD};
+pub use highlight::Highlight;
+pub use json::Problem;
 pub use repo::Repository;
+pub use style::{STYLE_COLLAPSED, STYLE_ELIDED, STYLE_FADED, STYLE_HIGHLIGHT, STYLE_MOVED};
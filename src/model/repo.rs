@@ -1,23 +1,21 @@
 use crate::parser::instructions::Instruction;
 use std::collections::{HashMap, HashSet};
 use crate::model::branch::Branch;
+use crate::model::errors::*;
 use crate::model::commit::Commit;
+use crate::model::group::Group;
+use crate::model::head::{Head, DETACHED_HEAD};
 
+#[derive(Clone, Default)]
 pub struct Repository {
     pub branches: HashMap<String, Branch>,
-    pub head: Option<String>,
+    pub head: Option<Head>,
     pub commits: HashMap<String, Commit>,
+    pub remotes: Vec<String>,
+    pub groups: Vec<Group>,
 }
 
 impl Repository {
-    pub fn default() -> Repository {
-        Repository {
-            branches: HashMap::new(),
-            head: None,
-            commits: HashMap::new(),
-        }
-    }
-
     pub fn commit_iter(&self) -> impl Iterator<Item=&Commit> {
         self.commits.values()
     }
@@ -26,42 +24,65 @@ impl Repository {
         self.branches.values()
     }
 
-    fn add_commit_and_history_to_hashset(&self, hash_set: &mut HashSet<String>,commit_id: &str) {
-        if hash_set.insert(commit_id.to_string()) {
-            for parent in self.commits.get(commit_id).unwrap().parents.iter() {
-                self.add_commit_and_history_to_hashset(hash_set, parent);
-            }
-        }
-    }
-
     pub fn branch_commits(&self, branch: &str) -> HashSet<String> {
         // Build a hash set of all commits in the branch
-        let head = if let Some(commit) =self.branch_head(branch) {
-            commit
-        } else {
-            return HashSet::new();
-        };
-        let mut commits = HashSet::new();
-        self.add_commit_and_history_to_hashset(&mut commits, head);
-
-        commits
+        self.ancestors(self.branch_head(branch))
     }
 
-    pub fn apply_instruction(&mut self, i: &Instruction) {
+    // Apply an instruction, which fails if it does not fit the repository,
+    // like one that names commits that do not exist
+    pub fn apply_instruction(&mut self, i: &Instruction) -> Result<()> {
+        let new_commits = match i {
+            Instruction::COMMIT(Some(id))
+            | Instruction::MERGE(id, _, _)
+            | Instruction::SQUASH(id, _, _) => vec![id],
+            Instruction::FETCH(_, _, new_commits) => new_commits.iter().collect(),
+            _ => Vec::new(),
+        };
+        for id in new_commits {
+            self.check_unused_commit_id(id)?;
+        }
         match i {
-            Instruction::BRANCH(name, args) => {
+            Instruction::BRANCH(name, _args) => {
                 self.add_branch(name.clone(), None);
             }
             Instruction::COMMIT(id) => {
-                self.commit(id.clone());
+                let id = id.clone().unwrap_or_else(|| self.unused_commit_id());
+                self.commit(id);
+            }
+            Instruction::CHECKOUT(target) => {
+                self.checkout(target);
+            }
+            Instruction::MERGE(commit_id, addition_parents, args) => {
+                if args.squash {
+                    self.squash_merge(commit_id.clone(), addition_parents)?;
+                } else {
+                    self.merge(commit_id.clone(), addition_parents)?;
+                }
+            }
+            Instruction::SQUASH(commit_id, from, to) => {
+                self.squash(commit_id.clone(), from, to)?;
+            }
+            Instruction::REMOTE(name) => {
+                self.add_remote(name);
             }
-            Instruction::CHECKOUT(branch) => {
-                self.checkout_branch(branch);
+            Instruction::PUSH(remote, branch) => {
+                self.push(remote, branch.as_ref());
             }
-            Instruction::MERGE(commit_id, addition_parents) => {
-                self.merge(commit_id.clone(), addition_parents);
+            Instruction::FETCH(remote, branch, new_commits) => {
+                self.fetch(remote, branch, new_commits);
+            }
+            Instruction::HIGHLIGHT(highlight) => {
+                self.highlight(highlight)?;
+            }
+            Instruction::NOTE(commit, text) => {
+                self.note(commit, text)?;
+            }
+            Instruction::GROUP(name, selection) => {
+                self.group(name, selection)?;
             }
         }
+        Ok(())
     }
 
     pub fn branch_head(&self, branch: &str) -> Option<&String> {
@@ -71,11 +92,23 @@ impl Repository {
     }
 
     pub fn current_commit(&self) -> Option<&String> {
-        self.head
-            .as_ref()
-            .and_then(|b| self.branch_head(b))
+        match &self.head {
+            Some(Head::Branch(b)) => self.branch_head(b),
+            Some(Head::Detached(commit)) => Some(commit),
+            None => None,
+        }
     }
 
+    // The checked out branch, None if nothing is checked out or HEAD is detached
+    pub fn current_branch(&self) -> Option<&String> {
+        match &self.head {
+            Some(Head::Branch(b)) => Some(b),
+            _ => None,
+        }
+    }
+
+    // Create a branch at the reference or what is checked out.
+    // An existing branch is moved there and keeps its place in the order of the branches.
     pub fn add_branch(&mut self, name: String, reference: Option<&String>) {
         let commit = if let Some(r) = reference {
            if self.commits.contains_key(r) {
@@ -86,87 +119,304 @@ impl Repository {
         } else {
             self.current_commit().cloned()
         };
+        let priority = self
+            .branches
+            .get(&name)
+            .map_or(self.branches.len(), |b| b.priority);
         self.branches.insert(
             name.clone(),
             Branch {
                 name: name.clone(),
                 style: "".to_string(),
-                priority: self.branches.len(),
+                priority,
                 current_commit: commit,
+                remote: None,
             },
         );
         // If its the only branch, do a checkout
         if self.head.is_none() {
-            self.head = Some(name);
+            self.head = Some(Head::Branch(name));
+        }
+    }
+
+    // Checkout a branch, or detach HEAD at a commit if there is no such branch.
+    // Remote-tracking branches cannot be checked out, HEAD is detached at their commit.
+    // If it is neither, a new branch is created.
+    pub fn checkout(&mut self, target: &str) {
+        let remote_commit = self
+            .branches
+            .get(target)
+            .filter(|b| b.remote.is_some())
+            .and_then(|b| b.current_commit.clone());
+        if let Some(commit) = remote_commit {
+            self.checkout_commit(&commit);
+        } else if !self.branches.contains_key(target) && self.commits.contains_key(target) {
+            self.checkout_commit(target);
+        } else {
+            self.checkout_branch(target);
         }
     }
 
+    pub fn checkout_commit(&mut self, commit: &str) {
+        self.head = Some(Head::Detached(commit.to_string()));
+    }
+
     pub fn checkout_branch(&mut self, name: &str) {
         if !self.branches.contains_key(name) {
             self.add_branch(name.to_string(), None);
         }
-        self.head = Some(name.to_string());
+        self.head = Some(Head::Branch(name.to_string()));
     }
 
     pub fn checkout_branch_at_commit(&mut self, name: &str, commit: &str) {
         if !self.branches.contains_key(name) {
             self.add_branch(name.to_string(), Some(&commit.to_string()));
         }
-        self.head = Some(name.to_string());
+        self.head = Some(Head::Branch(name.to_string()));
     }
 
     pub fn commit(&mut self, id: String) {
-        self.merge(id, &Vec::new());
+        if self.head.is_none() {
+            self.checkout_branch("main");
+        }
+        let parents = self.current_commit().cloned().into_iter().collect();
+        self.commit_on_head(id, parents);
+    }
+
+    // A commit id can only be used once, reusing one would rewrite history
+    pub fn check_unused_commit_id(&self, id: &str) -> Result<()> {
+        if self.commits.contains_key(id) {
+            bail!(ErrorKind::InvalidOperation(format!(
+                "commit `{}` already exists",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    // An id for a commit that has not been given one, like `C3`
+    pub fn unused_commit_id(&self) -> String {
+        (self.commits.len() + 1..)
+            .map(|n| format!("C{}", n))
+            .find(|id| !self.commits.contains_key(id))
+            .unwrap()
+    }
+
+    pub fn add_remote(&mut self, name: &str) {
+        if !self.remotes.iter().any(|r| r == name) {
+            self.remotes.push(name.to_string());
+        }
+    }
+
+    // Name of the branch tracking `branch` on `remote`
+    pub fn remote_ref_name(remote: &str, branch: &str) -> String {
+        format!("{}/{}", remote, branch)
+    }
+
+    // Point the remote-tracking branch to the given commit, creating it if needed
+    fn set_remote_ref(&mut self, remote: &str, branch: &str, commit: Option<String>) {
+        self.add_remote(remote);
+        let name = Repository::remote_ref_name(remote, branch);
+        let priority = self.branches.len();
+        self.branches
+            .entry(name.clone())
+            .or_insert(Branch {
+                name,
+                style: "".to_string(),
+                priority,
+                current_commit: None,
+                remote: Some(remote.to_string()),
+            })
+            .current_commit = commit;
+    }
+
+    // Push a branch (or the checked out branch) to the remote,
+    // which moves the remote-tracking branch to it
+    pub fn push(&mut self, remote: &str, branch: Option<&String>) {
+        let branch = match branch.or(self.current_branch()) {
+            Some(b) => b.clone(),
+            None => return,
+        };
+        let commit = self.branch_head(&branch).cloned();
+        self.set_remote_ref(remote, &branch, commit);
     }
 
-    pub fn merge(&mut self, id: String, add_branches: &Vec<String>) {
-        if self.head == None {
-            self.checkout_branch(&"main".to_string());
+    // Fetch a branch from the remote, on which the given commits have been made
+    // since the last fetch or push.
+    // The commits are added to the remote-tracking branch.
+    pub fn fetch(&mut self, remote: &str, branch: &str, new_commits: &[String]) {
+        let name = Repository::remote_ref_name(remote, branch);
+        let mut parent = match self.branches.get(&name) {
+            Some(remote_ref) => remote_ref.current_commit.clone(),
+            None => self.branch_head(branch).cloned(),
+        };
+        for id in new_commits {
+            self.add_commit(id.clone(), name.clone(), parent.into_iter().collect());
+            parent = Some(id.clone());
         }
+        self.set_remote_ref(remote, branch, parent);
+    }
+
+    // Annotate a commit, given by its id, a branch or HEAD
+    pub fn note(&mut self, revision: &str, text: &str) -> Result<()> {
+        let id = match self.resolve(revision) {
+            Some(id) => id.clone(),
+            None => bail!(ErrorKind::UnknownRevision(revision.to_string())),
+        };
+        self.commits.get_mut(&id).unwrap().notes.push(text.to_string());
+        Ok(())
+    }
 
-        if let Some(branch) = &self.head {
-            // Collect the parent commits
-            let mut parent_commits = Vec::new();
-            // Of our current branch
-            if let Some(branch_parent) = self.current_commit()
-            {
-                parent_commits.push(branch_parent.clone());
+    fn add_commit(&mut self, id: String, branch: String, parents: Vec<String>) {
+        self.commits.insert(
+            id.clone(),
+            Commit {
+                id,
+                time: self.commits.len(),
+                branch,
+                parents,
+                message: "".to_string(),
+                tags: Vec::new(),
+                style: "".to_string(),
+                squashed: Vec::new(),
+                elided_parents: Vec::new(),
+                notes: Vec::new(),
+            },
+        );
+    }
+
+    pub fn merge(&mut self, id: String, add_branches: &[String]) -> Result<()> {
+        // Collect the additional parent commits, of branches or just commits
+        let mut add_parents = Vec::new();
+        for branch in add_branches {
+            match self.branch_head(branch).or(self.commits.get(branch).map(|c| &c.id)) {
+                Some(commit) => add_parents.push(commit.clone()),
+                None => bail!(ErrorKind::UnknownRevision(branch.clone())),
             }
-            // And the additional branches
-            for branch in add_branches {
-                // Is it a branch?
-                if let Some(branch_commit) = self
-                    .branches
-                    .get(branch)
-                    .and_then(|b| b.current_commit.as_ref())
-                {
-                    parent_commits.push(branch_commit.clone());
-                }
-                // Or just a commit?
-                if self.commits.contains_key(branch) {
-                    parent_commits.push(branch.clone());
-                }
+        }
+        if self.head.is_none() {
+            self.checkout_branch("main");
+        }
+
+        // Of our current branch first
+        let mut parent_commits: Vec<String> = self.current_commit().cloned().into_iter().collect();
+        parent_commits.extend(add_parents);
+        self.commit_on_head(id, parent_commits);
+        Ok(())
+    }
+
+    // Merge the branches as one new commit, which only has the checked out commit as parent
+    // and records the commits it brings in
+    pub fn squash_merge(&mut self, id: String, add_branches: &[String]) -> Result<()> {
+        let mut sources = Vec::new();
+        for branch in add_branches {
+            match self.branch_head(branch).or(self.commits.get(branch).map(|c| &c.id)) {
+                Some(commit) => sources.push(commit),
+                None => bail!(ErrorKind::UnknownRevision(branch.clone())),
             }
+        }
+        let merged = self.ancestors(sources);
+        if self.head.is_none() {
+            self.checkout_branch("main");
+        }
+        let known = self.ancestors(self.current_commit());
+        let squashed = self.time_ordered(merged.difference(&known));
+        let parents = self.current_commit().cloned().into_iter().collect();
+        self.commit_on_head(id.clone(), parents);
+        self.commits.get_mut(&id).unwrap().squashed = squashed;
+        Ok(())
+    }
 
-            self.commits.insert(
-                id.clone(),
-                Commit {
-                    id: id.clone(),
-                    time: self.commits.len(),
-                    branch: branch.clone(),
-                    parents: parent_commits,
-                },
-            );
-            self.branches.get_mut(branch).unwrap().current_commit = Some(id.clone());
-        } else {
-            panic!("no branch, cannot commit")
+    // Replace the commits from `from` to `to`, following the first parents, by one new commit.
+    // `to` has to be checked out, which is then moved to the new commit. The replaced commits
+    // are kept, but only reachable through the new commit recording them.
+    pub fn squash(&mut self, id: String, from: &str, to: &str) -> Result<()> {
+        for revision in [from, to] {
+            if !self.commits.contains_key(revision) {
+                bail!(ErrorKind::UnknownRevision(revision.to_string()));
+            }
+        }
+        if self.current_commit().map(|c| c.as_str()) != Some(to) {
+            bail!(ErrorKind::InvalidOperation(format!(
+                "cannot squash up to `{}`, it is not checked out",
+                to
+            )));
+        }
+        let mut squashed = Vec::new();
+        let mut current = Some(to.to_string());
+        while let Some(commit) = current {
+            current = if commit == from {
+                None
+            } else {
+                self.commits[&commit].parents.first().cloned()
+            };
+            squashed.push(commit);
+        }
+        if squashed.last().map(|c| c.as_str()) != Some(from) {
+            bail!(ErrorKind::InvalidOperation(format!(
+                "cannot squash from `{}`, it is not a first parent ancestor of `{}`",
+                from, to
+            )));
+        }
+        squashed.reverse();
+        let parents = self.commits[from].parents.iter().take(1).cloned().collect();
+        self.commit_on_head(id.clone(), parents);
+        self.commits.get_mut(&id).unwrap().squashed = squashed;
+        Ok(())
+    }
+
+    // Add a commit where HEAD is and move the checked out branch, or the detached HEAD, to it
+    fn commit_on_head(&mut self, id: String, parents: Vec<String>) {
+        let head = match &self.head {
+            Some(head) => head.clone(),
+            None => panic!("no branch, cannot commit"),
+        };
+        let branch = match &head {
+            Head::Branch(b) => b.clone(),
+            Head::Detached(_) => DETACHED_HEAD.to_string(),
+        };
+        self.add_commit(id.clone(), branch.clone(), parents);
+        match head {
+            Head::Branch(_) => {
+                self.branches.get_mut(&branch).unwrap().current_commit = Some(id);
+            }
+            Head::Detached(_) => self.head = Some(Head::Detached(id)),
+        }
+    }
+
+    fn time_ordered<'a>(&self, ids: impl Iterator<Item = &'a String>) -> Vec<String> {
+        let mut ids = ids.cloned().collect::<Vec<String>>();
+        ids.sort_by_key(|id| self.commits[id].time);
+        ids
+    }
+
+    // The commits a branch or HEAD leads to
+    pub fn reachable_commits(&self) -> HashSet<String> {
+        let heads = self
+            .branches
+            .values()
+            .filter_map(|b| b.current_commit.as_ref())
+            .chain(self.current_commit());
+        self.ancestors(heads)
+    }
+
+    // A copy without the given commits, which must not be parents of the remaining ones
+    pub fn without_commits(&self, ids: &HashSet<String>) -> Repository {
+        let mut res = self.clone();
+        res.commits.retain(|id, _| !ids.contains(id));
+        let ordered = res.time_ordered(res.commits.keys());
+        for (time, id) in ordered.iter().enumerate() {
+            res.commits.get_mut(id).unwrap().time = time;
         }
+        res
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::options::layout::LayoutOptions;
+    use crate::view::View;
 
     #[test]
     fn empty_default() {
@@ -246,7 +496,7 @@ mod test {
         repo.commit("C".to_string());
         repo.checkout_branch("main");
         repo.commit("D".to_string());
-        repo.merge("M".to_string(), &vec!["feature1".to_string()]);
+        repo.merge("M".to_string(), &["feature1".to_string()]).unwrap();
 
         // Test
         assert_eq!(repo.branch_commits("main"), HashSet::from_iter(vec!["A".to_string(),"B".to_string(), "D".to_string(),"M".to_string()]));
@@ -267,11 +517,152 @@ mod test {
         repo.commit("C".to_string());
         repo.checkout_branch("main");
         repo.commit("D".to_string());
-        repo.merge("M".to_string(), &vec!["feature1".to_string(),"feature2".to_string()]);
+        repo.merge("M".to_string(), &["feature1".to_string(),"feature2".to_string()]).unwrap();
 
         // Test
         assert_eq!(repo.branch_commits("main"), HashSet::from_iter(vec!["A".to_string(),"B".to_string(),"C".to_string(), "D".to_string(),"M".to_string()]));
         assert_eq!(repo.branch_commits("feature1"), HashSet::from_iter(vec!["A".to_string(),"B".to_string()]));
         assert_eq!(repo.branch_commits("feature2"), HashSet::from_iter(vec!["A".to_string(),"C".to_string()]));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn detached_head() {
+        // Setup
+        let mut repo = Repository::default();
+
+        // Act
+        repo.commit("A".to_string());
+        repo.commit("B".to_string());
+        repo.checkout("A");
+        repo.commit("C".to_string());
+
+        // Test
+        assert_eq!(repo.head, Some(Head::Detached("C".to_string())));
+        assert_eq!(repo.current_commit(), Some(&"C".to_string()));
+        assert_eq!(repo.current_branch(), None);
+        assert_eq!(repo.commits["C"].parents, vec!["A".to_string()]);
+        assert_eq!(repo.commits["C"].branch, DETACHED_HEAD);
+        assert_eq!(repo.branch_head("main"), Some(&"B".to_string()));
+    }
+
+    #[test]
+    fn push_and_fetch() {
+        // Setup
+        let mut repo = Repository::default();
+
+        // Act
+        repo.commit("A".to_string());
+        repo.push("origin", None);
+        repo.commit("B".to_string());
+        repo.fetch("origin", "main", &["C".to_string(), "D".to_string()]);
+
+        // Test
+        assert_eq!(repo.remotes, vec!["origin".to_string()]);
+        let remote_ref = &repo.branches["origin/main"];
+        assert_eq!(remote_ref.remote, Some("origin".to_string()));
+        assert_eq!(remote_ref.current_commit, Some("D".to_string()));
+        assert_eq!(repo.commits["C"].parents, vec!["A".to_string()]);
+        assert_eq!(repo.commits["C"].branch, "origin/main");
+        assert_eq!(repo.branch_head("main"), Some(&"B".to_string()));
+
+        // Act
+        repo.merge("M".to_string(), &["origin/main".to_string()]).unwrap();
+        repo.push("origin", Some(&"main".to_string()));
+
+        // Test
+        assert_eq!(repo.branch_head("origin/main"), Some(&"M".to_string()));
+        assert_eq!(repo.current_branch(), Some(&"main".to_string()));
+        // The fetched commits are only reachable through the merge
+        let view = View::from_state(&repo, &LayoutOptions::default());
+        let mut ids = view.commits.keys().cloned().collect::<Vec<String>>();
+        ids.sort();
+        assert_eq!(ids, vec!["A", "B", "C", "D", "M"]);
+        assert!(view.lanes.iter().any(|l| l.commits.len() == 2
+            && l.commits.iter().all(|c| c.id == "C" || c.id == "D")));
+    }
+
+    #[test]
+    fn squash() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.commit("B".to_string());
+        repo.commit("C".to_string());
+        repo.commit("D".to_string());
+
+        // Act
+        repo.squash("S".to_string(), "B", "D").unwrap();
+
+        // Test
+        assert_eq!(repo.branch_head("main"), Some(&"S".to_string()));
+        assert_eq!(repo.commits["S"].parents, vec!["A".to_string()]);
+        assert_eq!(repo.commits["S"].squashed, vec!["B", "C", "D"]);
+        assert_eq!(repo.reachable_commits(), HashSet::from(["A".to_string(), "S".to_string()]));
+    }
+
+    #[test]
+    fn squash_merge() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.checkout_branch("feature");
+        repo.commit("B".to_string());
+        repo.commit("C".to_string());
+        repo.checkout_branch("main");
+        repo.commit("D".to_string());
+
+        // Act
+        repo.squash_merge("S".to_string(), &["feature".to_string()]).unwrap();
+        let unknown = repo.squash_merge("T".to_string(), &["nope".to_string()]);
+
+        // Test
+        assert_eq!(repo.commits["S"].parents, vec!["D".to_string()]);
+        assert_eq!(repo.commits["S"].squashed, vec!["B", "C"]);
+        assert_eq!(repo.branch_head("feature"), Some(&"C".to_string()));
+        assert_eq!(unknown.unwrap_err().to_string(), "unknown revision `nope`");
+        assert!(!repo.commits.contains_key("T"));
+    }
+
+    #[test]
+    fn squash_outside_first_parents() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.checkout_branch("feature");
+        repo.commit("X".to_string());
+        repo.checkout_branch("main");
+        repo.commit("B".to_string());
+        repo.commit("C".to_string());
+
+        // Act
+        let res = repo.squash("S".to_string(), "X", "C");
+
+        // Test
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "cannot squash from `X`, it is not a first parent ancestor of `C`"
+        );
+        assert!(!repo.commits.contains_key("S"));
+        assert_eq!(repo.branch_head("main"), Some(&"C".to_string()));
+    }
+
+    #[test]
+    fn squash_not_checked_out() {
+        // Setup
+        let mut repo = Repository::default();
+        for id in ["A", "B", "C", "D"] {
+            repo.commit(id.to_string());
+        }
+
+        // Act
+        let res = repo.squash("S".to_string(), "B", "C");
+
+        // Test
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "cannot squash up to `C`, it is not checked out"
+        );
+        assert!(!repo.commits.contains_key("S"));
+        assert_eq!(repo.branch_head("main"), Some(&"D".to_string()));
+    }
+}
@@ -0,0 +1,358 @@
+use super::commit::Commit;
+use super::head::{Head, DETACHED_HEAD};
+use super::highlight::Highlight;
+use super::repo::Repository;
+use crate::parser::instructions::{BranchArgs, Instruction, MergeArgs};
+
+// Instructions being written for a repository, applied to a repository of their own
+// to know where they lead
+struct Script<'a> {
+    target: &'a Repository,
+    state: Repository,
+    instructions: Vec<Instruction>,
+}
+
+impl Repository {
+    // Instructions that build this repository, e.g. to convert one read from YAML.
+    // Commits are made in time order on their parents, branches are moved back to a parent
+    // where the history went on without them. Remote-tracking branches are only moved by
+    // pushing and fetching, if they were moved otherwise they stay where the instructions lead.
+    pub fn to_instructions(&self) -> Vec<Instruction> {
+        let mut script = Script {
+            target: self,
+            state: Repository::default(),
+            instructions: Vec::new(),
+        };
+        for remote in &self.remotes {
+            script.emit(Instruction::REMOTE(remote.clone()));
+        }
+        let mut commits = self.commits.values().collect::<Vec<&Commit>>();
+        commits.sort_by_key(|c| c.time);
+        for commit in commits {
+            script.add_commit(commit);
+        }
+        script.finish();
+        script.instructions
+    }
+
+    // The instructions as a script, one per line
+    pub fn to_script(&self) -> String {
+        self.to_instructions()
+            .iter()
+            .map(|i| format!("{}\n", i))
+            .collect()
+    }
+}
+
+impl Script<'_> {
+    fn emit(&mut self, instruction: Instruction) {
+        // Instructions are only written for what the state they are applied to has
+        self.state.apply_instruction(&instruction).unwrap();
+        self.instructions.push(instruction);
+    }
+
+    // How a commit is named as merge source: a local branch pointing to it, or its id
+    fn reference(&self, id: &String) -> String {
+        let mut names = self
+            .state
+            .branches
+            .values()
+            .filter(|b| b.remote.is_none() && b.current_commit.as_ref() == Some(id))
+            .map(|b| &b.name)
+            .filter(|name| !self.target.commits.contains_key(*name))
+            .collect::<Vec<&String>>();
+        names.sort();
+        names.first().map_or(id.clone(), |name| (*name).clone())
+    }
+
+    // Check out the branch, or detach HEAD, at the given parent
+    fn check_out(&mut self, branch: &str, parent: Option<&String>) {
+        if branch == DETACHED_HEAD {
+            let detached_at_parent =
+                matches!(&self.state.head, Some(Head::Detached(c)) if Some(c) == parent);
+            if let (false, Some(parent)) = (detached_at_parent, parent) {
+                self.emit(Instruction::CHECKOUT(parent.clone()));
+            }
+            return;
+        }
+        if let Some(parent) = parent.filter(|&p| self.state.branch_head(branch) != Some(p)) {
+            // New branches start at what is checked out,
+            // existing ones are moved there
+            if self.state.current_commit() != Some(parent) {
+                self.emit(Instruction::CHECKOUT(parent.clone()));
+            }
+            if self.state.branches.contains_key(branch) {
+                self.emit(Instruction::BRANCH(
+                    branch.to_string(),
+                    BranchArgs::default(),
+                ));
+            }
+        }
+        if self.state.current_branch().map(|b| b.as_str()) != Some(branch) {
+            self.emit(Instruction::CHECKOUT(branch.to_string()));
+        }
+    }
+
+    fn add_commit(&mut self, commit: &Commit) {
+        let remote = self
+            .target
+            .branches
+            .get(&commit.branch)
+            .and_then(|b| b.remote.clone());
+        if let Some(remote) = remote {
+            self.add_fetched_commit(commit, &remote);
+            return;
+        }
+
+        let first_parent = commit.parents.first();
+        let squashed_from = commit
+            .squashed
+            .first()
+            .map(|id| self.target.commits[id].parents.first());
+        // Where the branch of the commit is, with the commits written so far
+        let branch_tip = match &self.state.head {
+            Some(Head::Detached(c)) if commit.branch == DETACHED_HEAD => Some(c),
+            _ => self.state.branch_head(&commit.branch),
+        };
+        let instruction = if squashed_from == Some(first_parent)
+            && commit.parents.len() <= 1
+            && branch_tip == commit.squashed.last()
+        {
+            // Replacing commits on the branch
+            self.check_out(&commit.branch, None);
+            Instruction::SQUASH(
+                commit.id.clone(),
+                commit.squashed[0].clone(),
+                commit.squashed.last().unwrap().clone(),
+            )
+        } else if !commit.squashed.is_empty() {
+            // Merging the tips of the squashed commits
+            self.check_out(&commit.branch, first_parent);
+            let tips = commit
+                .squashed
+                .iter()
+                .filter(|id| {
+                    !commit
+                        .squashed
+                        .iter()
+                        .any(|other| self.target.commits[other].parents.contains(id))
+                })
+                .map(|id| self.reference(id))
+                .collect();
+            Instruction::MERGE(commit.id.clone(), tips, MergeArgs { squash: true })
+        } else if commit.parents.len() > 1 {
+            self.check_out(&commit.branch, first_parent);
+            let sources = commit
+                .parents
+                .iter()
+                .skip(1)
+                .map(|id| self.reference(id))
+                .collect();
+            Instruction::MERGE(commit.id.clone(), sources, MergeArgs::default())
+        } else {
+            self.check_out(&commit.branch, first_parent);
+            Instruction::COMMIT(Some(commit.id.clone()))
+        };
+        self.emit(instruction);
+        self.push_to(&commit.id);
+    }
+
+    // Commits of remote-tracking branches are fetched, consecutive ones at once
+    fn add_fetched_commit(&mut self, commit: &Commit, remote: &str) {
+        let local = commit.branch[remote.len() + 1..].to_string();
+        self.state.fetch(remote, &local, std::slice::from_ref(&commit.id));
+        match self.instructions.last_mut() {
+            Some(Instruction::FETCH(r, b, commits)) if r == remote && *b == local => {
+                commits.push(commit.id.clone())
+            }
+            _ => self.instructions.push(Instruction::FETCH(
+                remote.to_string(),
+                local,
+                vec![commit.id.clone()],
+            )),
+        }
+    }
+
+    // Where a remote-tracking branch has been pushed to: the parent of its first fetched
+    // commit, or where it ends if nothing has been fetched
+    fn pushed_commit(&self, remote_ref: &str) -> Option<&String> {
+        let first_fetched = self
+            .target
+            .commits
+            .values()
+            .filter(|c| c.branch == remote_ref)
+            .min_by_key(|c| c.time);
+        match first_fetched {
+            Some(commit) => commit.parents.first(),
+            None => self.target.branch_head(remote_ref),
+        }
+    }
+
+    // Push the branches whose remote-tracking branches have been pushed to the new commit
+    fn push_to(&mut self, id: &String) {
+        let mut pushes = Vec::new();
+        for branch in self.target.branches.values() {
+            if let Some(remote) = &branch.remote {
+                let local = &branch.name[remote.len() + 1..];
+                if self.pushed_commit(&branch.name) == Some(id)
+                    && self.state.branch_head(local) == Some(id)
+                {
+                    pushes.push((remote.clone(), local.to_string()));
+                }
+            }
+        }
+        pushes.sort();
+        for (remote, local) in pushes {
+            self.emit(Instruction::PUSH(remote, Some(local)));
+        }
+    }
+
+    // Create the branches without commits of their own, move the others to where they end,
+    // check out what was checked out, annotate the commits and group them
+    fn finish(&mut self) {
+        let mut branches = self
+            .target
+            .branches
+            .values()
+            .filter(|b| {
+                b.remote.is_none()
+                    && (!self.state.branches.contains_key(&b.name)
+                        || (b.current_commit.is_some()
+                            && self.state.branch_head(&b.name) != b.current_commit.as_ref()))
+            })
+            .collect::<Vec<_>>();
+        branches.sort_by_key(|b| b.priority);
+        for branch in branches {
+            if let Some(commit) = &branch.current_commit {
+                if self.state.current_commit() != Some(commit) {
+                    self.emit(Instruction::CHECKOUT(commit.clone()));
+                }
+            }
+            self.emit(Instruction::BRANCH(branch.name.clone(), BranchArgs::default()));
+        }
+        match &self.target.head {
+            Some(Head::Branch(branch)) if self.state.current_branch() != Some(branch) => {
+                self.emit(Instruction::CHECKOUT(branch.clone()))
+            }
+            Some(Head::Detached(commit)) if self.state.head != self.target.head => {
+                self.emit(Instruction::CHECKOUT(commit.clone()))
+            }
+            _ => {}
+        }
+        let mut commits = self.target.commits.values().collect::<Vec<&Commit>>();
+        commits.sort_by_key(|c| c.time);
+        for commit in commits {
+            for note in &commit.notes {
+                self.emit(Instruction::NOTE(commit.id.clone(), note.clone()));
+            }
+        }
+        for group in &self.target.groups {
+            let commits = Highlight::Commits(group.commits.clone());
+            self.emit(Instruction::GROUP(group.name.clone(), commits));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    // Everything the instructions can express
+    fn assert_same(a: &Repository, b: &Repository) {
+        let mut ids = a.commits.keys().collect::<Vec<_>>();
+        ids.sort();
+        let mut other_ids = b.commits.keys().collect::<Vec<_>>();
+        other_ids.sort();
+        assert_eq!(ids, other_ids);
+        for (id, commit) in &a.commits {
+            let other = &b.commits[id];
+            assert_eq!(commit.parents, other.parents, "parents of {}", id);
+            assert_eq!(commit.branch, other.branch, "branch of {}", id);
+            assert_eq!(commit.squashed, other.squashed, "squashed by {}", id);
+            assert_eq!(commit.notes, other.notes, "notes of {}", id);
+        }
+        let heads = |r: &Repository| {
+            let mut heads = r
+                .branches
+                .values()
+                .map(|b| (b.name.clone(), b.current_commit.clone()))
+                .collect::<Vec<_>>();
+            heads.sort();
+            heads
+        };
+        assert_eq!(heads(a), heads(b));
+        assert_eq!(a.head, b.head);
+        assert_eq!(a.remotes, b.remotes);
+        assert_eq!(a.groups, b.groups);
+    }
+
+    #[test]
+    fn round_trip() {
+        // Setup
+        let instructions = parse_git_instructions(
+            "
+            commit(A)
+            push(origin)
+            checkout(feature)
+            commit(B)
+            commit(C)
+            checkout(main)
+            commit(D)
+            merge(M, feature)
+            checkout(fix)
+            commit(E)
+            commit(F)
+            squash(S, E, F)
+            checkout(main)
+            merge(N, fix, squash=true)
+            fetch(origin, main, G, H)
+            checkout(B)
+            branch(old)
+            commit(X)
+            note(B, \"the bug was introduced here\")
+            note(H, fixed)
+            group(\"PR #1\", from=B, to=C)
+            ",
+        )
+        .unwrap();
+        // main goes on from a commit of feature
+        let continued_elsewhere = Repository::from_json(
+            r#"{
+                "commits": [
+                    {"id": "A", "branch": "main"},
+                    {"id": "B", "branch": "feature", "parents": ["A"]},
+                    {"id": "C", "branch": "main", "parents": ["B"]}
+                ],
+                "branches": [
+                    {"name": "main", "commit": "C"},
+                    {"name": "feature", "commit": "B"}
+                ],
+                "head": {"branch": "main"}
+            }"#,
+        )
+        .unwrap();
+        // main was reset to A before C was made
+        let reset = Repository::from_json(
+            r#"{
+                "commits": [
+                    {"id": "A", "branch": "main"},
+                    {"id": "B", "branch": "main", "parents": ["A"]},
+                    {"id": "C", "branch": "main", "parents": ["A"]}
+                ],
+                "branches": [{"name": "main", "commit": "C"}],
+                "head": {"branch": "main"}
+            }"#,
+        )
+        .unwrap();
+
+        for repo in [instructions, continued_elsewhere, reset] {
+            // Act
+            let script = repo.to_script();
+            let rebuilt = parse_git_instructions(&script).unwrap();
+
+            // Test
+            assert_same(&repo, &rebuilt);
+        }
+    }
+}
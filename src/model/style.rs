@@ -1,3 +1,13 @@
+// Styles of commits and connectors in the view
+pub const STYLE_FADED: &str = "faded";
+pub const STYLE_HIGHLIGHT: &str = "highlight";
+// The arrow of a ref that points to another commit than before
+pub const STYLE_MOVED: &str = "moved";
+// A node standing for a run of commits
+pub const STYLE_COLLAPSED: &str = "collapsed";
+// The connector to history that has been left out
+pub const STYLE_ELIDED: &str = "elided";
+
 struct Color {
     r: u8,
     g: u8,
@@ -1,22 +1,155 @@
+use crate::options::pattern::glob_match;
+use std::collections::HashMap;
+
 pub enum LayoutDirection {
     UP,
     RIGHT,
 }
 
+// How commits are distributed over the rows of the diagram
+pub enum RowMode {
+    // Every commit gets its own row, in the order the commits were created
+    Chronological,
+    // A commit is placed one row below its lowest parent,
+    // so commits on independent lanes can share a row
+    Compact,
+}
+
+// How branches are assigned to the columns of the diagram
+pub enum LaneAllocation {
+    // Every branch keeps its own column for the whole diagram
+    PerBranch,
+    // Columns are reused once the lane that occupied them has ended
+    Reuse,
+}
+
+// The order of the lanes from left to right
+pub enum BranchOrder {
+    // In the order the branches were created
+    Creation,
+    // The listed branches first, in the given order, then the remaining ones
+    Explicit(Vec<String>),
+    // Groups of branches matching the glob patterns, e.g. `main`, `release/*`, `feature/*`.
+    // Branches matching no pattern come last.
+    Groups(Vec<String>),
+    // The given branch in the center, the other branches on both sides of it
+    CenterMain(String),
+}
+
+impl BranchOrder {
+    // The group a branch is ordered into, lower groups are further left
+    pub fn group(&self, branch_name: &str) -> usize {
+        let position = match self {
+            BranchOrder::Creation => Some(0),
+            BranchOrder::Explicit(names) => names.iter().position(|n| n == branch_name),
+            BranchOrder::Groups(patterns) => {
+                patterns.iter().position(|p| glob_match(p, branch_name))
+            }
+            BranchOrder::CenterMain(main) => Some(if main == branch_name { 0 } else { 1 }),
+        };
+        match (position, self) {
+            (Some(p), _) => p,
+            (None, BranchOrder::Explicit(list)) | (None, BranchOrder::Groups(list)) => list.len(),
+            (None, _) => 0,
+        }
+    }
+}
+
+// Shape of the connectors to parents outside of the lane
+pub enum EdgeRouting {
+    // Direct line from commit to parent
+    Straight,
+    // Along the lane, then with a right angle to the parent
+    Orthogonal,
+    // Like orthogonal, but the corner is rounded with the given radius
+    Rounded(f64),
+    // Smooth curve leaving and entering the commits vertically
+    Bezier,
+}
+
+// How the labels of several branches pointing to the same commit are arranged
+pub enum LabelStacking {
+    // Next to each other
+    Horizontal,
+    // On top of each other
+    Vertical,
+}
+
+// Where remote-tracking branches like `origin/main` are placed
+pub enum RemoteRefs {
+    // Right next to the local branch of the same name
+    BesideLocal,
+    // After all local branches
+    Separate,
+}
+
+// How the commits replaced by a squash are shown
+pub enum SquashDisplay {
+    // Only the squashed commit, unless the replaced commits are still on a branch
+    Collapsed,
+    // The replaced commits too, with a bracket naming the commit they were squashed into
+    Expanded,
+}
+
+// Corner of the diagram, e.g. for the legend
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
 pub struct LayoutOptions {
     pub graph_direction: LayoutDirection,
+    pub row_mode: RowMode,
+    pub lane_allocation: LaneAllocation,
+    pub branch_order: BranchOrder,
+    pub remote_refs: RemoteRefs,
+    pub edge_routing: EdgeRouting,
+    pub label_stacking: LabelStacking,
+    pub squash_display: SquashDisplay,
+    // Runs of at least this many commits of a lane, without branches, merges, tags or refs,
+    // are drawn as one node
+    pub collapse_runs: Option<usize>,
+    // Commits that are never collapsed into a run
+    pub visible_commits: Vec<String>,
+    // Branches that always get the given column
+    pub pinned_lanes: HashMap<String, usize>,
     pub commit_hist_dist: usize,
     pub branch_dist: usize,
     pub commit_radius: usize,
+    // Size of all texts, in the same units as the distances
+    pub font_size: f64,
+    // Texts above and below the diagram
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub caption: Option<String>,
+    // Where the legend of the symbols used is placed, no legend if none
+    pub legend: Option<Corner>,
 }
 
-impl LayoutOptions {
-    pub fn default() -> LayoutOptions {
+impl Default for LayoutOptions {
+    fn default() -> LayoutOptions {
         LayoutOptions {
             graph_direction: LayoutDirection::UP,
+            row_mode: RowMode::Chronological,
+            lane_allocation: LaneAllocation::PerBranch,
+            branch_order: BranchOrder::Creation,
+            remote_refs: RemoteRefs::BesideLocal,
+            edge_routing: EdgeRouting::Orthogonal,
+            label_stacking: LabelStacking::Horizontal,
+            squash_display: SquashDisplay::Collapsed,
+            collapse_runs: None,
+            visible_commits: Vec::new(),
+            pinned_lanes: HashMap::new(),
             commit_hist_dist: 3,
             branch_dist: 2,
             commit_radius: 1,
+            font_size: 0.35,
+            title: None,
+            subtitle: None,
+            caption: None,
+            legend: None,
         }
     }
 }
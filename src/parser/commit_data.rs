@@ -8,7 +8,7 @@ pub struct CommitData {
 }
 
 pub fn build_commit_data(
-    branch_datas: &Vec<BranchData>,
+    branch_datas: &[BranchData],
     commit_index: usize,
 ) -> Option<CommitData> {
     let mut commit_and_branch: Option<(String, String)> = None;
@@ -1,32 +1,17 @@
-use super::errors::*;
-use lazy_static::lazy_static;
-use regex::Regex;
+use super::diagnostic::{Diagnostic, DiagnosticKind, Span};
+use super::syntax::Arg;
 
 #[derive(Clone)]
 pub struct NamedArg {
     pub name: String,
     pub value: String,
-}
-
-impl NamedArg {
-    fn new(name: String, value: String) -> NamedArg {
-        NamedArg { name, value }
-    }
-
-    pub fn parse(arg: &str) -> Option<NamedArg> {
-        lazy_static! {
-            static ref NAMED_ARG_RE: Regex = Regex::new(r"^\s*([a-zA-Z]+)\s*=(.+)$").unwrap();
-        }
-        NAMED_ARG_RE.captures(arg).map(|res| NamedArg {
-            name: res.get(1).unwrap().as_str().trim().to_string(),
-            value: res.get(2).unwrap().as_str().trim().to_string(),
-        })
-    }
+    pub span: Span,
 }
 
 #[derive(Clone)]
 pub struct ArgList {
     pub plain_args: Vec<String>,
+    pub plain_spans: Vec<Span>,
     pub named_args: Vec<NamedArg>,
 }
 
@@ -34,23 +19,38 @@ impl ArgList {
     fn new() -> ArgList {
         ArgList {
             plain_args: Vec::new(),
+            plain_spans: Vec::new(),
             named_args: Vec::new(),
         }
     }
 
-    pub fn parse(args: Vec<String>, line_num: usize) -> Result<ArgList> {
-        args.iter().try_fold(ArgList::new(), |list, arg| {
-            let mut res = list.clone();
-            match NamedArg::parse(arg) {
-                Some(n) => res.named_args.push(n),
+    pub fn parse(args: Vec<Arg>, input: &str) -> Result<ArgList, Diagnostic> {
+        let mut res = ArgList::new();
+        for arg in args {
+            match arg.name {
+                Some(name) => res.named_args.push(NamedArg {
+                    name,
+                    value: arg.value,
+                    span: arg.span,
+                }),
                 None => {
-                    if list.named_args.len() > 0 {
-                        bail!(ErrorKind::NamedArgAfterPlainArg(line_num))
+                    if let Some(named) = res.named_args.last() {
+                        return Err(Diagnostic::in_input(
+                            DiagnosticKind::NamedArgAfterPlainArg,
+                            "plain argument after named argument".to_string(),
+                            arg.span,
+                            input,
+                        )
+                        .with_hint(format!(
+                            "move `{}` before `{}={}`",
+                            arg.value, named.name, named.value
+                        )));
                     }
-                    res.plain_args.push(arg.clone())
+                    res.plain_args.push(arg.value);
+                    res.plain_spans.push(arg.span);
                 }
             };
-            Ok(res)
-        })
+        }
+        Ok(res)
     }
 }
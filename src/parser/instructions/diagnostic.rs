@@ -0,0 +1,140 @@
+use std::fmt;
+
+// Part of a line of the input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    // Starting at 1
+    pub line: usize,
+    // Byte offsets into the line, the end is exclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, start: usize, end: usize) -> Span {
+        Span { line, start, end }
+    }
+
+    // The span covering both, or just this one if they are on different lines
+    pub fn to(&self, other: &Span) -> Span {
+        if self.line != other.line {
+            return *self;
+        }
+        Span::new(
+            self.line,
+            self.start.min(other.start),
+            self.end.max(other.end),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    InvalidInstruction,
+    WrongNumberOfArguments,
+    InvalidArgument,
+    NamedArgAfterPlainArg,
+    // The instruction is valid but cannot be applied to the repository built so far
+    InvalidOperation,
+}
+
+// A problem in the input, with where it is and how it might be fixed
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub span: Span,
+    // The line the span points into
+    pub source_line: String,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, message: String, span: Span, source_line: &str) -> Diagnostic {
+        Diagnostic {
+            kind,
+            message,
+            span,
+            source_line: source_line.to_string(),
+            hint: None,
+        }
+    }
+
+    // A diagnostic for a span in the whole input
+    pub fn in_input(kind: DiagnosticKind, message: String, span: Span, input: &str) -> Diagnostic {
+        let line = input.split('\n').nth(span.line - 1).unwrap_or("");
+        Diagnostic::new(kind, message, span, line)
+    }
+
+    pub fn with_hint(mut self, hint: String) -> Diagnostic {
+        self.hint = Some(hint);
+        self
+    }
+
+    // Column of the start of the span, starting at 1
+    pub fn column(&self) -> usize {
+        self.source_line[..self.span.start].chars().count() + 1
+    }
+
+    // The message with the line, and the span underlined by carets
+    pub fn render(&self) -> String {
+        let line_num = self.span.line.to_string();
+        let gutter = " ".repeat(line_num.len());
+        let carets = self.source_line[self.span.start..self.span.end]
+            .chars()
+            .count()
+            .max(1);
+        let mut res = format!(
+            "error: {}\n{}--> line {}, column {}\n{} |\n{} | {}\n{} | {}{}",
+            self.message,
+            gutter,
+            self.span.line,
+            self.column(),
+            gutter,
+            line_num,
+            self.source_line,
+            gutter,
+            " ".repeat(self.column() - 1),
+            "^".repeat(carets)
+        );
+        if let Some(hint) = &self.hint {
+            res.push_str(&format!("\n{} = hint: {}", gutter, hint));
+        }
+        res
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        // Setup
+        let diagnostic = Diagnostic::new(
+            DiagnosticKind::InvalidInstruction,
+            "unknown instruction `comit`".to_string(),
+            Span::new(12, 4, 9),
+            "    comit(A)",
+        )
+        .with_hint("did you mean `commit`?".to_string());
+
+        // Act
+        let rendered = diagnostic.render();
+
+        // Test
+        assert_eq!(
+            rendered,
+            "error: unknown instruction `comit`\n  \
+             --> line 12, column 5\n   |\n\
+             12 |     comit(A)\n   |     ^^^^^\n   \
+             = hint: did you mean `commit`?"
+        );
+    }
+}
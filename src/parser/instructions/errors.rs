@@ -1,3 +1,4 @@
+use super::diagnostic::Diagnostic;
 
 error_chain! {
     types {
@@ -9,21 +10,9 @@ error_chain! {
     }
 
     errors {
-        InvalidInstruction(line: String, line_num: usize) {
-            description("invalid instruction"),
-            display("invalid instruction on line {}: {}", line_num, line),
-        }
-        WorngNumberOfArguemtns(line: String, line_num: usize) {
-            description("wrong number of arguments"),
-            display("wrong number of arguments on line {}: {}", line_num, line),
-        }
-        InvalidArgument(command: String, arg: String, line_num: usize) {
-            description("invalid argument"),
-            display("invalid argument to {} on line {}: {}", command, line_num, arg),
-        }
-        NamedArgAfterPlainArg(line_num: usize) {
-            description("plain arg after named arg"),
-            display("plain arg after named arg on line {}", line_num),
+        InvalidInstructions(diagnostics: Vec<Diagnostic>) {
+            description("invalid instructions"),
+            display("{}", diagnostics.iter().map(|d| d.render()).collect::<Vec<String>>().join("\n\n")),
         }
     }
 }
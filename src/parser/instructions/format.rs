@@ -0,0 +1,120 @@
+use super::errors::*;
+use super::instruction::Instruction;
+use super::lexer::tokenize;
+use super::syntax::parse_calls;
+
+// A line of the formatted script, with the lines of the input it comes from
+struct Entry {
+    first_line: usize,
+    last_line: usize,
+    // Comments come before the instruction they are placed in
+    is_comment: bool,
+    text: String,
+}
+
+// The script written the canonical way: one instruction per line, with the same
+// spacing and quoting everywhere. Comments are kept, and blank lines between
+// instructions are reduced to one.
+pub fn format_script(input: &str) -> Result<String> {
+    let (calls, mut diagnostics) = parse_calls(input);
+    let mut entries = Vec::new();
+    for call in calls {
+        let (first_line, last_line) = (call.command_span.line, call.close_span.line);
+        match Instruction::from_call(call, input) {
+            Ok(instruction) => entries.push(Entry {
+                first_line,
+                last_line,
+                is_comment: false,
+                text: instruction.to_string(),
+            }),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    if !diagnostics.is_empty() {
+        diagnostics.sort_by_key(|d| (d.span.line, d.span.start));
+        bail!(ErrorKind::InvalidInstructions(diagnostics));
+    }
+
+    let (_, comments, _) = tokenize(input);
+    for comment in comments {
+        let text = format!("#{}", comment.text.trim_end());
+        let line = comment.span.line;
+        // Comments on the last line of an instruction stay behind it
+        if let Some(entry) = entries
+            .iter_mut()
+            .find(|e| !e.is_comment && e.last_line == line)
+        {
+            entry.text = format!("{}  {}", entry.text, text);
+            continue;
+        }
+        // Comments inside an instruction go before it
+        let line = entries
+            .iter()
+            .find(|e| !e.is_comment && e.first_line <= line && line <= e.last_line)
+            .map(|e| e.first_line)
+            .unwrap_or(line);
+        entries.push(Entry {
+            first_line: line,
+            last_line: line,
+            is_comment: true,
+            text,
+        });
+    }
+    entries.sort_by_key(|e| (e.first_line, !e.is_comment));
+
+    let mut res = String::new();
+    let mut last_line: Option<usize> = None;
+    for entry in entries {
+        if last_line.is_some_and(|last| entry.first_line > last + 1) {
+            res.push('\n');
+        }
+        res.push_str(&entry.text);
+        res.push('\n');
+        last_line = Some(last_line.map_or(entry.last_line, |l| l.max(entry.last_line)));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonical() {
+        // Setup
+        let input = "
+            # Setup
+            commit( A )
+            merge(M,feature ,squash = true)   # squashed
+            merge(N, # the merge commit
+                  \"main\", \"a b\")
+
+
+            push(origin)";
+
+        // Act
+        let formatted = format_script(input).unwrap();
+
+        // Test
+        assert_eq!(
+            formatted,
+            "# Setup\n\
+             commit(A)\n\
+             merge(M, feature, squash=true)  # squashed\n\
+             # the merge commit\n\
+             merge(N, main, \"a b\")\n\
+             \n\
+             push(origin)\n"
+        );
+        assert_eq!(format_script(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn invalid() {
+        // Act
+        let res = format_script("commit(A)\nmerge(M)");
+
+        // Test
+        assert!(res.is_err());
+    }
+}
@@ -1,18 +1,16 @@
-use super::errors::*;
-use crate::parser::instructions::arguments::ArgList;
-use lazy_static::lazy_static;
-use regex::Regex;
+use super::diagnostic::{Diagnostic, DiagnosticKind, Span};
+use super::lexer::is_word;
+use super::syntax::Call;
+use crate::model::Highlight;
+use crate::parser::instructions::arguments::{ArgList, NamedArg};
+use std::fmt;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct BranchArgs {
     new_root: bool,
 }
 
 impl BranchArgs {
-    fn default() -> BranchArgs {
-        BranchArgs { new_root: false }
-    }
-
     fn with_new_root(&self) -> BranchArgs {
         let mut res = self.clone();
         res.new_root = true;
@@ -20,83 +18,336 @@ impl BranchArgs {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeArgs {
+    pub squash: bool,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Instruction {
     BRANCH(String, BranchArgs),
-    COMMIT(String),
+    // Without an id, one is made up
+    COMMIT(Option<String>),
     CHECKOUT(String),
-    MERGE(String, Vec<String>),
+    MERGE(String, Vec<String>, MergeArgs),
+    SQUASH(String, String, String),
+    REMOTE(String),
+    PUSH(String, Option<String>),
+    FETCH(String, String, Vec<String>),
+    HIGHLIGHT(Highlight),
+    NOTE(String, String),
+    // A name and the commits in the group, selected like for highlighting
+    GROUP(String, Highlight),
 }
 
+// The instructions with their smallest and largest number of plain arguments, and how they are used
+const COMMANDS: &[(&str, usize, Option<usize>, &str)] = &[
+    ("branch", 1, Some(1), "branch(name, new_root=true)"),
+    ("commit", 0, Some(1), "commit(id)"),
+    ("checkout", 1, Some(1), "checkout(branch_or_commit)"),
+    ("merge", 2, None, "merge(id, branch..., squash=true)"),
+    ("squash", 3, Some(3), "squash(id, from_commit, to_commit)"),
+    ("remote", 1, Some(1), "remote(name)"),
+    ("push", 1, Some(2), "push(remote, branch)"),
+    ("fetch", 2, None, "fetch(remote, branch, commit...)"),
+    (
+        "highlight",
+        0,
+        None,
+        "highlight(commit...) or highlight(branch=name) or highlight(range=from..to) or highlight(from=commit, to=commit)",
+    ),
+    ("note", 2, Some(2), "note(commit, \"text\")"),
+    (
+        "group",
+        1,
+        None,
+        "group(name, commit...) or group(name, branch=name) or group(name, range=from..to) or group(name, from=commit, to=commit)",
+    ),
+];
+
 impl Instruction {
-    pub fn from_command(command: String, args: ArgList, line_num: usize) -> Result<Instruction> {
+    pub fn from_command(
+        command: &str,
+        command_span: Span,
+        args: ArgList,
+        close_span: Span,
+        input: &str,
+    ) -> Result<Instruction, Diagnostic> {
+        let &(_, min, max, usage) = match COMMANDS.iter().find(|c| c.0 == command) {
+            Some(c) => c,
+            None => {
+                let hint = match Instruction::similar_command(command) {
+                    Some(similar) => format!("did you mean `{}`?", similar),
+                    None => format!(
+                        "known instructions are {}",
+                        COMMANDS
+                            .iter()
+                            .map(|c| c.0)
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    ),
+                };
+                return Err(Diagnostic::in_input(
+                    DiagnosticKind::InvalidInstruction,
+                    format!("unknown instruction `{}`", command),
+                    command_span,
+                    input,
+                )
+                .with_hint(hint));
+            }
+        };
+
+        // Check the number of arguments
+        let count = args.plain_args.len();
+        if count < min || max.is_some_and(|max| count > max) {
+            let expected = match max {
+                Some(max) if max == min => format!("{}", min),
+                Some(max) if min == 0 => format!("at most {}", max),
+                Some(max) => format!("{} to {}", min, max),
+                None => format!("at least {}", min),
+            };
+            // Point to the superfluous arguments, if there are, or to the end of the call
+            let span = match max {
+                Some(max) if count > max => args.plain_spans[max].to(&args.plain_spans[count - 1]),
+                _ => close_span,
+            };
+            return Err(Diagnostic::in_input(
+                DiagnosticKind::WrongNumberOfArguments,
+                format!(
+                    "wrong number of arguments to `{}`: expected {}, found {}",
+                    command, expected, count
+                ),
+                span,
+                input,
+            )
+            .with_hint(format!("usage: {}", usage)));
+        }
+        let invalid_argument = |arg: &NamedArg| {
+            Diagnostic::in_input(
+                DiagnosticKind::InvalidArgument,
+                format!(
+                    "invalid argument to `{}`: {}={}",
+                    command, arg.name, arg.value
+                ),
+                arg.span,
+                input,
+            )
+            .with_hint(format!("usage: {}", usage))
+        };
+
         // Handle the command
-        match command.as_str() {
+        match command {
             "branch" => {
-                if args.plain_args.len() != 1 {
-                    bail!(ErrorKind::WorngNumberOfArguemtns(command, line_num));
-                }
                 let branch_args =
                     args.named_args
                         .iter()
-                        .skip(1)
                         .try_fold(BranchArgs::default(), |ba, arg| match arg.name.as_str() {
-                            "new_root" => Result::Ok(ba.with_new_root()),
-                            _ => bail!(ErrorKind::InvalidArgument(
-                                command.clone(),
-                                arg.name.clone(),
-                                line_num
-                            )),
+                            "new_root" => Ok(ba.with_new_root()),
+                            _ => Err(invalid_argument(arg)),
                         })?;
                 Ok(Instruction::BRANCH(args.plain_args[0].clone(), branch_args))
             }
-            "commit" => {
-                if args.plain_args.len() != 1 || args.named_args.len() != 0 {
-                    bail!(ErrorKind::WorngNumberOfArguemtns(command, line_num));
-                }
-                Ok(Instruction::COMMIT(args.plain_args[0].clone()))
-            }
-            "checkout" => {
-                if args.plain_args.len() != 1 || args.named_args.len() != 0 {
-                    bail!(ErrorKind::WorngNumberOfArguemtns(command, line_num));
-                }
-                Ok(Instruction::CHECKOUT(args.plain_args[0].clone()))
-            }
             "merge" => {
-                if args.plain_args.len() < 2 || args.named_args.len() != 0 {
-                    bail!(ErrorKind::WorngNumberOfArguemtns(command, line_num));
-                }
+                let merge_args =
+                    args.named_args
+                        .iter()
+                        .try_fold(MergeArgs::default(), |ma, arg| {
+                            match (arg.name.as_str(), arg.value.as_str()) {
+                                ("squash", "true") => Ok(MergeArgs { squash: true }),
+                                ("squash", "false") => Ok(ma),
+                                _ => Err(invalid_argument(arg)),
+                            }
+                        })?;
                 Ok(Instruction::MERGE(
                     args.plain_args[0].clone(),
-                    args.plain_args.iter().skip(1).map(|a| a.clone()).collect(),
+                    args.plain_args.iter().skip(1).cloned().collect(),
+                    merge_args,
                 ))
             }
+            "highlight" | "group" => {
+                // A group is named by its first argument
+                let (name, plain) = match command {
+                    "group" => (Some(&args.plain_args[0]), &args.plain_args[1..]),
+                    _ => (None, &args.plain_args[..]),
+                };
+                let named = |name: &str| args.named_args.iter().find(|a| a.name == name);
+                if let Some(arg) = args
+                    .named_args
+                    .iter()
+                    .find(|a| !["branch", "range", "from", "to"].contains(&a.name.as_str()))
+                {
+                    return Err(invalid_argument(arg));
+                }
+                // Exactly one way to select the commits
+                let highlight = match (named("branch"), named("range"), named("from"), named("to"))
+                {
+                    (None, None, None, None) if !plain.is_empty() => {
+                        Highlight::Commits(plain.to_vec())
+                    }
+                    (Some(branch), None, None, None) if plain.is_empty() => {
+                        Highlight::Branch(branch.value.clone())
+                    }
+                    (None, Some(range), None, None) if plain.is_empty() => {
+                        Highlight::Range(range.value.clone())
+                    }
+                    (None, None, Some(from), Some(to)) if plain.is_empty() => {
+                        Highlight::Path(from.value.clone(), to.value.clone())
+                    }
+                    _ => {
+                        return Err(Diagnostic::in_input(
+                            DiagnosticKind::InvalidArgument,
+                            format!("`{}` needs commits, a branch, a range or a path", command),
+                            command_span.to(&close_span),
+                            input,
+                        )
+                        .with_hint(format!("usage: {}", usage)))
+                    }
+                };
+                Ok(match name {
+                    Some(name) => Instruction::GROUP(name.clone(), highlight),
+                    None => Instruction::HIGHLIGHT(highlight),
+                })
+            }
             _ => {
-                bail!(ErrorKind::InvalidInstruction(command, line_num));
+                // The other instructions have no named arguments
+                if let Some(arg) = args.named_args.first() {
+                    return Err(invalid_argument(arg));
+                }
+                let plain = &args.plain_args;
+                Ok(match command {
+                    "commit" => Instruction::COMMIT(plain.first().cloned()),
+                    "checkout" => Instruction::CHECKOUT(plain[0].clone()),
+                    "squash" => {
+                        Instruction::SQUASH(plain[0].clone(), plain[1].clone(), plain[2].clone())
+                    }
+                    "remote" => Instruction::REMOTE(plain[0].clone()),
+                    "push" => Instruction::PUSH(plain[0].clone(), plain.get(1).cloned()),
+                    "fetch" => Instruction::FETCH(
+                        plain[0].clone(),
+                        plain[1].clone(),
+                        plain.iter().skip(2).cloned().collect(),
+                    ),
+                    "note" => Instruction::NOTE(plain[0].clone(), plain[1].clone()),
+                    _ => unreachable!("instruction missing in COMMANDS"),
+                })
             }
         }
     }
 
-    pub fn from_line(line: &str, line_num: usize) -> Result<Instruction> {
-        lazy_static! {
-            static ref COMMAND_RE: Regex =
-                Regex::new(r"^(\s*[a-z]+)\((?:([^,\n]+),)*([^,\n]+)\)\s*$").unwrap();
-        }
-        // Parse the instruction
-        match COMMAND_RE.captures(line) {
-            None => {
-                bail!(ErrorKind::InvalidInstruction(line.to_string(), line_num));
+    // A known instruction that is only a typo away from the given one
+    fn similar_command(command: &str) -> Option<&'static str> {
+        COMMANDS
+            .iter()
+            .map(|c| (edit_distance(c.0, command), c.0))
+            .filter(|&(distance, _)| distance <= 2)
+            .min()
+            .map(|(_, name)| name)
+    }
+
+    // The instruction of a parsed call, checking its arguments
+    pub fn from_call(call: Call, input: &str) -> Result<Instruction, Diagnostic> {
+        let args = ArgList::parse(call.args, input)?;
+        Instruction::from_command(
+            &call.command,
+            call.command_span,
+            args,
+            call.close_span,
+            input,
+        )
+    }
+}
+
+// Written like it is parsed
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (command, args) = match self {
+            Instruction::BRANCH(name, args) => {
+                let mut res = vec![quoted(name)];
+                if args.new_root {
+                    res.push("new_root=true".to_string());
+                }
+                ("branch", res)
             }
-            Some(cs) => {
-                let command = cs.get(1).unwrap().as_str().to_string();
-                let mut args = Vec::new();
-                for i in 2..cs.len() {
-                    if let Some(c) = cs.get(i) {
-                        args.push(c.as_str().to_string());
-                    }
+            Instruction::COMMIT(id) => ("commit", id.iter().map(|id| quoted(id)).collect()),
+            Instruction::CHECKOUT(target) => ("checkout", vec![quoted(target)]),
+            Instruction::MERGE(id, branches, args) => {
+                let mut res = vec![quoted(id)];
+                res.extend(branches.iter().map(|b| quoted(b)));
+                if args.squash {
+                    res.push("squash=true".to_string());
                 }
-                Instruction::from_command(command, ArgList::parse(args, line_num)?, line_num)
+                ("merge", res)
+            }
+            Instruction::SQUASH(id, from, to) => {
+                ("squash", vec![quoted(id), quoted(from), quoted(to)])
+            }
+            Instruction::REMOTE(name) => ("remote", vec![quoted(name)]),
+            Instruction::PUSH(remote, branch) => {
+                let mut res = vec![quoted(remote)];
+                res.extend(branch.iter().map(|b| quoted(b)));
+                ("push", res)
             }
+            Instruction::FETCH(remote, branch, commits) => {
+                let mut res = vec![quoted(remote), quoted(branch)];
+                res.extend(commits.iter().map(|c| quoted(c)));
+                ("fetch", res)
+            }
+            Instruction::HIGHLIGHT(highlight) => ("highlight", selection_args(highlight)),
+            Instruction::NOTE(commit, text) => ("note", vec![quoted(commit), quoted(text)]),
+            Instruction::GROUP(name, selection) => {
+                let mut res = vec![quoted(name)];
+                res.extend(selection_args(selection));
+                ("group", res)
+            }
+        };
+        write!(f, "{}({})", command, args.join(", "))
+    }
+}
+
+// The arguments selecting the commits of a highlight or group
+fn selection_args(selection: &Highlight) -> Vec<String> {
+    match selection {
+        Highlight::Commits(commits) => commits.iter().map(|c| quoted(c)).collect(),
+        Highlight::Branch(branch) => vec![format!("branch={}", quoted(branch))],
+        Highlight::Range(range) => vec![format!("range={}", quoted(range))],
+        Highlight::Path(from, to) => vec![
+            format!("from={}", quoted(from)),
+            format!("to={}", quoted(to)),
+        ],
+    }
+}
+
+// The text as an argument, in quotes if needed
+pub fn quoted(text: &str) -> String {
+    if is_word(text) {
+        return text.to_string();
+    }
+    let mut res = "\"".to_string();
+    for c in text.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+// Number of characters to insert, delete or replace to turn one text into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(ca != cb);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
         }
+        previous = current;
     }
+    previous[b.len()]
 }
@@ -0,0 +1,182 @@
+use super::diagnostic::{Diagnostic, DiagnosticKind, Span};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    // Unquoted text, like a command, a branch name or a commit id
+    Word(String),
+    // Quoted text, with the escapes resolved
+    Str(String),
+    LParen,
+    RParen,
+    Comma,
+    Equals,
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+// A `#` comment, up to the end of its line
+#[derive(Clone, Debug)]
+pub struct Comment {
+    // The text after the `#`
+    pub text: String,
+    pub span: Span,
+}
+
+// Characters that end a word
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "(),=\"#".contains(c)
+}
+
+// Whether the text can be written without quotes
+pub fn is_word(text: &str) -> bool {
+    !text.is_empty() && !text.chars().any(is_delimiter)
+}
+
+// Split the input into tokens, skipping whitespace and putting `#` comments aside.
+// Problems are collected, the lexer continues after them.
+pub fn tokenize(input: &str) -> (Vec<Token>, Vec<Comment>, Vec<Diagnostic>) {
+    let mut tokens = Vec::new();
+    let mut comments = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (index, line) in input.split('\n').enumerate() {
+        let line_num = index + 1;
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            let kind = match c {
+                '#' => {
+                    comments.push(Comment {
+                        text: line[start + 1..].to_string(),
+                        span: Span::new(line_num, start, line.len()),
+                    });
+                    break;
+                }
+                c if c.is_whitespace() => continue,
+                '(' => TokenKind::LParen,
+                ')' => TokenKind::RParen,
+                ',' => TokenKind::Comma,
+                '=' => TokenKind::Equals,
+                '"' => {
+                    let mut text = String::new();
+                    let mut terminated = false;
+                    while let Some((position, c)) = chars.next() {
+                        match c {
+                            '"' => {
+                                terminated = true;
+                                break;
+                            }
+                            '\\' => match chars.next() {
+                                Some((_, '"')) => text.push('"'),
+                                Some((_, '\\')) => text.push('\\'),
+                                Some((_, 'n')) => text.push('\n'),
+                                Some((_, 't')) => text.push('\t'),
+                                Some((end, c)) => diagnostics.push(
+                                    Diagnostic::new(
+                                        DiagnosticKind::InvalidArgument,
+                                        format!("unknown escape `\\{}`", c),
+                                        Span::new(line_num, position, end + c.len_utf8()),
+                                        line,
+                                    )
+                                    .with_hint(
+                                        "known escapes are \\\", \\\\, \\n and \\t".to_string(),
+                                    ),
+                                ),
+                                None => break,
+                            },
+                            c => text.push(c),
+                        }
+                    }
+                    if !terminated {
+                        diagnostics.push(
+                            Diagnostic::new(
+                                DiagnosticKind::InvalidInstruction,
+                                "unterminated string".to_string(),
+                                Span::new(line_num, start, line.len()),
+                                line,
+                            )
+                            .with_hint("close the string with `\"` on the same line".to_string()),
+                        );
+                    }
+                    TokenKind::Str(text)
+                }
+                c => {
+                    let mut text = c.to_string();
+                    while let Some(&(_, c)) = chars.peek() {
+                        if is_delimiter(c) {
+                            break;
+                        }
+                        text.push(c);
+                        chars.next();
+                    }
+                    TokenKind::Word(text)
+                }
+            };
+            let end = chars.peek().map(|&(end, _)| end).unwrap_or(line.len());
+            tokens.push(Token {
+                kind,
+                span: Span::new(line_num, start, end),
+            });
+        }
+    }
+    (tokens, comments, diagnostics)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        let (tokens, _, diagnostics) = tokenize(input);
+        assert!(diagnostics.is_empty());
+        tokens.into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn tokens() {
+        // Act
+        let kinds = kinds("merge(M, \"a, \\\"b\\\"\", squash=true) # done\n)");
+
+        // Test
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Word("merge".to_string()),
+                TokenKind::LParen,
+                TokenKind::Word("M".to_string()),
+                TokenKind::Comma,
+                TokenKind::Str("a, \"b\"".to_string()),
+                TokenKind::Comma,
+                TokenKind::Word("squash".to_string()),
+                TokenKind::Equals,
+                TokenKind::Word("true".to_string()),
+                TokenKind::RParen,
+                TokenKind::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn spans() {
+        // Act
+        let (tokens, _, _) = tokenize("commit(A)\n  checkout(\"x\")");
+
+        // Test
+        assert_eq!(tokens[2].span, Span::new(1, 7, 8));
+        assert_eq!(tokens[4].span, Span::new(2, 2, 10));
+        assert_eq!(tokens[6].span, Span::new(2, 11, 14));
+    }
+
+    #[test]
+    fn unterminated_string() {
+        // Act
+        let (tokens, _, diagnostics) = tokenize("commit(\"A)\ncommit(B)");
+
+        // Test
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Span::new(1, 7, 10));
+        assert_eq!(tokens[3].kind, TokenKind::Word("commit".to_string()));
+    }
+}
@@ -1,7 +1,13 @@
-mod errors;
+mod diagnostic;
+pub mod errors;
+mod format;
 mod instruction;
+mod lexer;
 mod parser;
 pub use instruction::*;
 mod arguments;
+mod syntax;
 
-pub use parser::parse_git_instructions;
+pub use diagnostic::{Diagnostic, DiagnosticKind, Span};
+pub use format::format_script;
+pub use parser::{parse_git_instructions, parse_instructions};
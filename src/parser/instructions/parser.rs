@@ -1,22 +1,318 @@
+use super::diagnostic::{Diagnostic, DiagnosticKind, Span};
 use super::errors::*;
 use super::instruction::*;
+use super::syntax::parse_calls;
 use crate::model::repo::*;
 
+// The instructions of the input, or all problems found in it
+pub fn parse_instructions(input: &str) -> std::result::Result<Vec<Instruction>, Vec<Diagnostic>> {
+    Ok(parse_located_instructions(input)?
+        .into_iter()
+        .map(|(instruction, _)| instruction)
+        .collect())
+}
+
+// The instructions with the span of their command up to the closing parenthesis
+fn parse_located_instructions(
+    input: &str,
+) -> std::result::Result<Vec<(Instruction, Span)>, Vec<Diagnostic>> {
+    let (calls, mut diagnostics) = parse_calls(input);
+    let mut instructions = Vec::new();
+    for call in calls {
+        let span = call.command_span.to(&call.close_span);
+        match Instruction::from_call(call, input) {
+            Ok(instruction) => instructions.push((instruction, span)),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    if !diagnostics.is_empty() {
+        diagnostics.sort_by_key(|d| (d.span.line, d.span.start));
+        return Err(diagnostics);
+    }
+    Ok(instructions)
+}
+
 pub fn parse_git_instructions(input: &str) -> Result<Repository> {
-    // Go through input line by line
-    let lines = input.split("\n");
+    let instructions = match parse_located_instructions(input) {
+        Ok(instructions) => instructions,
+        Err(diagnostics) => bail!(ErrorKind::InvalidInstructions(diagnostics)),
+    };
+    // Instructions that cannot be applied are reported, the others are still applied
+    // to find all problems at once
     let mut state = Repository::default();
-    for (line_num, line) in lines.enumerate() {
-        let line = line.trim();
-        // Ignore empty instructions
-        if line.is_empty() {
-            continue;
+    let mut diagnostics = Vec::new();
+    // Highlights are applied last, to the repository as it ends up,
+    // so that they also cover the commits made after them
+    let (highlights, others): (Vec<_>, Vec<_>) = instructions
+        .iter()
+        .partition(|(instruction, _)| matches!(instruction, Instruction::HIGHLIGHT(_)));
+    for (instruction, span) in others.into_iter().chain(highlights) {
+        if let Err(e) = state.apply_instruction(instruction) {
+            diagnostics.push(Diagnostic::in_input(
+                DiagnosticKind::InvalidOperation,
+                e.to_string(),
+                *span,
+                input,
+            ));
+        }
+    }
+    if !diagnostics.is_empty() {
+        diagnostics.sort_by_key(|d| (d.span.line, d.span.start));
+        bail!(ErrorKind::InvalidInstructions(diagnostics));
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::Highlight;
+    use quickcheck::{Arbitrary, Gen, QuickCheck};
+
+    fn diagnostics(input: &str) -> Vec<Diagnostic> {
+        match parse_git_instructions(input) {
+            Err(Error(ErrorKind::InvalidInstructions(diagnostics), _)) => diagnostics,
+            _ => panic!("no diagnostics"),
         }
+    }
+
+    #[test]
+    fn valid_instructions() {
+        // Setup
+        let input = "commit(A)\n  squash(S, A, A)\nmerge(M, main, squash=true)";
 
-        // Parse the instruction
-        let command = Instruction::from_line(line, line_num)?;
+        // Act
+        let repo = parse_git_instructions(input).unwrap();
 
-        state.apply_instruction(&command);
+        // Test
+        assert_eq!(repo.commits["M"].squashed, Vec::<String>::new());
+        assert_eq!(repo.branch_head("main"), Some(&"M".to_string()));
+    }
+
+    #[test]
+    fn collects_errors() {
+        // Setup
+        let input = "commit(A)\ncomit(B)\n\n  squash(S, A)\nmerge(M, x, fast=true)\ncommit(C";
+
+        // Act
+        let diagnostics = diagnostics(input);
+
+        // Test
+        let kinds = diagnostics.iter().map(|d| &d.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                &DiagnosticKind::InvalidInstruction,
+                &DiagnosticKind::WrongNumberOfArguments,
+                &DiagnosticKind::InvalidArgument,
+                &DiagnosticKind::InvalidInstruction,
+            ]
+        );
+        assert_eq!(diagnostics[0].span, Span::new(2, 0, 5));
+        assert_eq!(
+            diagnostics[0].hint,
+            Some("did you mean `commit`?".to_string())
+        );
+        assert_eq!(diagnostics[1].span, Span::new(4, 13, 14));
+        assert_eq!(diagnostics[1].source_line, "  squash(S, A)");
+        assert_eq!(diagnostics[2].span, Span::new(5, 12, 21));
+        assert_eq!(diagnostics[3].span.line, 6);
+    }
+
+    #[test]
+    fn invalid_operations() {
+        // Act
+        let diagnostics = diagnostics(
+            "commit(A)\ncommit(B)\nsquash(S, A, nope)\nmerge(M, x, squash=true)\nhighlight(A, y)\nnote(z, text)\ngroup(G, A, nope)\nmerge(M, w)",
+        );
+
+        // Test
+        assert_eq!(diagnostics.len(), 6);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.kind == DiagnosticKind::InvalidOperation));
+        assert_eq!(diagnostics[0].message, "unknown revision `nope`");
+        assert_eq!(diagnostics[0].span, Span::new(3, 0, 18));
+        assert_eq!(diagnostics[1].message, "unknown revision `x`");
+        assert_eq!(diagnostics[1].span.line, 4);
+        assert_eq!(diagnostics[2].message, "unknown revision `y`");
+        assert_eq!(diagnostics[3].message, "unknown revision `z`");
+        assert_eq!(diagnostics[4].message, "unknown revision `nope`");
+        assert_eq!(diagnostics[4].span.line, 7);
+        assert_eq!(diagnostics[5].message, "unknown revision `w`");
+    }
+
+    #[test]
+    fn reused_commit_ids() {
+        // Act
+        let diagnostics = diagnostics(
+            "commit(A)\ncommit(A)\ncheckout(f)\nmerge(A, main)\nsquash(A, A, A)\nfetch(origin, main, B, A)",
+        );
+
+        // Test
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.kind == DiagnosticKind::InvalidOperation
+                && d.message == "commit `A` already exists"));
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| d.span.line)
+                .collect::<Vec<usize>>(),
+            vec![2, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn superfluous_arguments() {
+        // Act
+        let diagnostics = diagnostics("commit(A, B, C)");
+
+        // Test
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "wrong number of arguments to `commit`: expected at most 1, found 3"
+        );
+        assert_eq!(diagnostics[0].span, Span::new(1, 10, 14));
+        assert_eq!(
+            diagnostics[0].render(),
+            "error: wrong number of arguments to `commit`: expected at most 1, found 3\n \
+             --> line 1, column 11\n  |\n\
+             1 | commit(A, B, C)\n  |           ^^^^\n  \
+             = hint: usage: commit(id)"
+        );
+    }
+
+    #[test]
+    fn highlights() {
+        // Act
+        let instructions = parse_instructions(
+            "highlight(A, B)\nhighlight(branch=feature)\nhighlight(range=main..feature)\nhighlight(from=A, to=M)",
+        )
+        .unwrap();
+        let group = parse_instructions("group(\"PR #42\", from=A, to=M)").unwrap();
+        let diagnostics = diagnostics("highlight(A, branch=feature)");
+
+        // Test
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::HIGHLIGHT(Highlight::Commits(vec!["A".to_string(), "B".to_string()])),
+                Instruction::HIGHLIGHT(Highlight::Branch("feature".to_string())),
+                Instruction::HIGHLIGHT(Highlight::Range("main..feature".to_string())),
+                Instruction::HIGHLIGHT(Highlight::Path("A".to_string(), "M".to_string())),
+            ]
+        );
+        assert_eq!(
+            instructions[2].to_string(),
+            "highlight(range=main..feature)"
+        );
+        assert_eq!(
+            group,
+            vec![Instruction::GROUP(
+                "PR #42".to_string(),
+                Highlight::Path("A".to_string(), "M".to_string())
+            )]
+        );
+        assert_eq!(group[0].to_string(), "group(\"PR #42\", from=A, to=M)");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`highlight` needs commits, a branch, a range or a path"
+        );
+    }
+
+    // Instructions with arbitrary names and ids
+    #[derive(Clone, Debug)]
+    struct Script(Vec<Instruction>);
+
+    impl Arbitrary for Script {
+        fn arbitrary(g: &mut Gen) -> Script {
+            let text = |g: &mut Gen| String::arbitrary(g);
+            // Every element on its own, so lists of different texts are tested too
+            let texts = |g: &mut Gen| {
+                (0..1 + usize::arbitrary(g) % 3)
+                    .map(|_| text(g))
+                    .collect::<Vec<String>>()
+            };
+            let instructions = (0..usize::arbitrary(g) % 8)
+                .map(|_| match usize::arbitrary(g) % 14 {
+                    0 => Instruction::BRANCH(text(g), BranchArgs::default()),
+                    1 => Instruction::COMMIT(Option::arbitrary(g)),
+                    2 => Instruction::CHECKOUT(text(g)),
+                    3 => Instruction::MERGE(
+                        text(g),
+                        texts(g),
+                        MergeArgs {
+                            squash: bool::arbitrary(g),
+                        },
+                    ),
+                    4 => Instruction::SQUASH(text(g), text(g), text(g)),
+                    5 => Instruction::REMOTE(text(g)),
+                    6 => Instruction::PUSH(text(g), Option::arbitrary(g)),
+                    7 => Instruction::FETCH(text(g), text(g), Vec::arbitrary(g)),
+                    8 => Instruction::HIGHLIGHT(Highlight::Commits(texts(g))),
+                    9 => Instruction::HIGHLIGHT(Highlight::Branch(text(g))),
+                    10 => Instruction::HIGHLIGHT(Highlight::Range(text(g))),
+                    11 => Instruction::HIGHLIGHT(Highlight::Path(text(g), text(g))),
+                    12 => Instruction::NOTE(text(g), text(g)),
+                    _ => Instruction::GROUP(text(g), Highlight::Range(text(g))),
+                })
+                .collect();
+            Script(instructions)
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        fn printed_and_parsed(script: Script) -> bool {
+            let printed = script
+                .0
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<String>>()
+                .join("\n");
+            match parse_instructions(&printed) {
+                Ok(parsed) => parsed == script.0,
+                Err(_) => false,
+            }
+        }
+        QuickCheck::new()
+            .tests(500)
+            .quickcheck(printed_and_parsed as fn(Script) -> bool);
+    }
+
+    #[test]
+    fn comments_and_multi_line_calls() {
+        // Setup
+        let input = "
+            # Two commits on main
+            commit(A)  # the first one
+            commit()
+            merge(
+                \"merge #1\",
+                main,
+            )
+            ";
+
+        // Act
+        let instructions = parse_instructions(input).unwrap();
+
+        // Test
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::COMMIT(Some("A".to_string())),
+                Instruction::COMMIT(None),
+                Instruction::MERGE(
+                    "merge #1".to_string(),
+                    vec!["main".to_string()],
+                    MergeArgs::default()
+                ),
+            ]
+        );
+        assert_eq!(instructions[2].to_string(), "merge(\"merge #1\", main)");
     }
-    Ok(state)
 }
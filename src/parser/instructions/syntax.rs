@@ -0,0 +1,276 @@
+use super::diagnostic::{Diagnostic, DiagnosticKind, Span};
+use super::lexer::{tokenize, Token, TokenKind};
+
+// An argument of a call, `value` or `name=value`
+#[derive(Clone, Debug)]
+pub struct Arg {
+    pub name: Option<String>,
+    pub value: String,
+    pub span: Span,
+}
+
+// A command with its arguments, like `merge(M, feature)`
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub command: String,
+    pub command_span: Span,
+    pub args: Vec<Arg>,
+    // The closing parenthesis
+    pub close_span: Span,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+// Parse the calls of the input. Calls may span several lines.
+// Problems are collected, the parser continues with the next call.
+pub fn parse_calls(input: &str) -> (Vec<Call>, Vec<Diagnostic>) {
+    let (tokens, _, mut diagnostics) = tokenize(input);
+    let mut parser = Parser {
+        input,
+        tokens,
+        position: 0,
+    };
+    let mut calls = Vec::new();
+    while parser.position < parser.tokens.len() {
+        let start = parser.position;
+        match parser.call() {
+            Ok(call) => calls.push(call),
+            Err(diagnostic) => {
+                diagnostics.push(diagnostic);
+                parser.recover(start);
+            }
+        }
+    }
+    (calls, diagnostics)
+}
+
+// How a token is named in messages
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Word(word) => format!("`{}`", word),
+        TokenKind::Str(_) => "a string".to_string(),
+        TokenKind::LParen => "`(`".to_string(),
+        TokenKind::RParen => "`)`".to_string(),
+        TokenKind::Comma => "`,`".to_string(),
+        TokenKind::Equals => "`=`".to_string(),
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // Whether a call starts at the position, used to continue after a problem
+    fn starts_call(&self, position: usize) -> bool {
+        match (self.tokens.get(position), self.tokens.get(position + 1)) {
+            (Some(command), Some(paren)) => {
+                matches!(command.kind, TokenKind::Word(_)) && paren.kind == TokenKind::LParen
+            }
+            _ => false,
+        }
+    }
+
+    // Skip to after the closing parenthesis, or to where the next call starts,
+    // looking again at everything after the start of the failed call
+    fn recover(&mut self, start: usize) {
+        self.position = start + 1;
+        while let Some(token) = self.peek() {
+            if token.kind == TokenKind::RParen {
+                self.position += 1;
+                return;
+            }
+            if self.starts_call(self.position) {
+                return;
+            }
+            self.position += 1;
+        }
+    }
+
+    // Problem at the token, or at the end of the input if there are no more tokens
+    fn unexpected(&self, token: Option<&Token>, expected: &str) -> Diagnostic {
+        let (span, found) = match token {
+            Some(token) => (token.span, describe(&token.kind)),
+            None => {
+                let lines = self.input.split('\n').collect::<Vec<&str>>();
+                let end = lines.last().map(|l| l.len()).unwrap_or(0);
+                (
+                    Span::new(lines.len(), end, end),
+                    "the end of the input".to_string(),
+                )
+            }
+        };
+        Diagnostic::in_input(
+            DiagnosticKind::InvalidInstruction,
+            format!("expected {}, found {}", expected, found),
+            span,
+            self.input,
+        )
+    }
+
+    fn call(&mut self) -> Result<Call, Diagnostic> {
+        let (command, command_span) = match self.next() {
+            Some(Token {
+                kind: TokenKind::Word(command),
+                span,
+                ..
+            }) => (command, span),
+            token => {
+                return Err(self
+                    .unexpected(token.as_ref(), "an instruction")
+                    .with_hint("instructions look like `commit(A)`".to_string()))
+            }
+        };
+        match self.next() {
+            Some(Token {
+                kind: TokenKind::LParen,
+                ..
+            }) => {}
+            token => {
+                return Err(self
+                    .unexpected(token.as_ref(), &format!("`(` after `{}`", command))
+                    .with_hint("instructions look like `commit(A)`".to_string()))
+            }
+        }
+        let mut args = Vec::new();
+        loop {
+            // A closing parenthesis is allowed after the opening one or a comma
+            if let Some(Token {
+                kind: TokenKind::RParen,
+                span,
+                ..
+            }) = self.peek()
+            {
+                let close_span = *span;
+                self.position += 1;
+                return Ok(Call {
+                    command,
+                    command_span,
+                    args,
+                    close_span,
+                });
+            }
+            args.push(self.arg()?);
+            match self.next() {
+                Some(Token {
+                    kind: TokenKind::Comma,
+                    ..
+                }) => {}
+                Some(Token {
+                    kind: TokenKind::RParen,
+                    span,
+                    ..
+                }) => {
+                    return Ok(Call {
+                        command,
+                        command_span,
+                        args,
+                        close_span: span,
+                    })
+                }
+                token => return Err(self.unexpected(token.as_ref(), "`,` or `)`")),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<(String, Span), Diagnostic> {
+        match self.next() {
+            Some(Token {
+                kind: TokenKind::Word(text) | TokenKind::Str(text),
+                span,
+                ..
+            }) => Ok((text, span)),
+            token => Err(self.unexpected(token.as_ref(), "an argument")),
+        }
+    }
+
+    fn arg(&mut self) -> Result<Arg, Diagnostic> {
+        let name_token = self.peek().cloned();
+        let (value, span) = self.value()?;
+        if let Some(Token {
+            kind: TokenKind::Equals,
+            ..
+        }) = self.peek()
+        {
+            // Only words name arguments
+            if let Some(Token {
+                kind: TokenKind::Str(_),
+                ..
+            }) = name_token
+            {
+                return Err(self.unexpected(name_token.as_ref(), "an argument name"));
+            }
+            self.position += 1;
+            let (named_value, value_span) = self.value()?;
+            return Ok(Arg {
+                name: Some(value),
+                value: named_value,
+                span: span.to(&value_span),
+            });
+        }
+        Ok(Arg {
+            name: None,
+            value,
+            span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn multi_line_call() {
+        // Act
+        let (calls, diagnostics) = parse_calls(
+            "merge(M, # the merge commit\n  feature1,\n  \"feature 2\",\n  squash=true,\n)\ncommit()",
+        );
+
+        // Test
+        assert!(diagnostics.is_empty());
+        assert_eq!(calls.len(), 2);
+        let args = &calls[0].args;
+        assert_eq!(
+            args.iter().map(|a| a.value.as_str()).collect::<Vec<&str>>(),
+            vec!["M", "feature1", "feature 2", "true"]
+        );
+        assert_eq!(args[3].name, Some("squash".to_string()));
+        assert_eq!(args[3].span, Span::new(4, 2, 13));
+        assert_eq!(calls[0].close_span, Span::new(5, 0, 1));
+        assert!(calls[1].args.is_empty());
+    }
+
+    #[test]
+    fn recovers() {
+        // Act
+        let (calls, diagnostics) = parse_calls("commit(A B)\ncommit(C\ncommit(D)\n)commit(E)");
+
+        // Test
+        assert_eq!(
+            calls
+                .iter()
+                .map(|c| c.args[0].value.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["D", "E"]
+        );
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].message, "expected `,` or `)`, found `B`");
+        assert_eq!(
+            diagnostics[1].message,
+            "expected `,` or `)`, found `commit`"
+        );
+        assert_eq!(diagnostics[1].span, Span::new(3, 0, 6));
+        assert_eq!(diagnostics[2].message, "expected an instruction, found `)`");
+    }
+}
@@ -1,5 +1,9 @@
+// The ascii graph parser is not wired up yet
+#[allow(dead_code)]
 mod branch_data;
+#[allow(dead_code)]
 mod commit_data;
 pub mod instructions;
+#[allow(clippy::module_inception, unused_imports)]
 pub mod parser;
-mod yaml;
+pub mod yaml;
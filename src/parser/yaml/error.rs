@@ -2,6 +2,9 @@ error_chain! {
     types {
         Error, ErrorKind, ResultExt, Result;
     }
+    links {
+        Model(crate::model::errors::Error, crate::model::errors::ErrorKind);
+    }
     foreign_links {
         Parse(::serde_yaml::Error);
     }
@@ -3,7 +3,8 @@ use serde::{Serialize, Deserialize};
 #[derive(Serialize, Deserialize)]
 pub struct CommitCommand {
     #[serde(rename = "name")]
-    pub(crate) name: String
+    pub(crate) name: String,
+    pub(crate) note: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -20,13 +21,23 @@ pub struct MergeCommand {
     pub(crate) branches: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct GroupCommand {
+    #[serde(rename = "group")]
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) commits: Vec<String>,
+    pub(crate) range: Option<String>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Command {
     Commit(CommitCommand),
     SimpleCommit(String),
     Branch(BranchCommand),
-    Merge(MergeCommand)
+    Merge(MergeCommand),
+    Group(GroupCommand),
 
 }
 
@@ -1,3 +1,5 @@
 mod file;
 mod repo_builder;
-mod error;
\ No newline at end of file
+mod error;
+
+pub use repo_builder::build_repo as parse_yaml;
@@ -1,18 +1,23 @@
-use crate::model::Repository;
+use crate::model::{Highlight, Repository};
 use crate::parser::yaml::file::Command;
 use super::error::*;
 use super::file::YamlFile;
 
-fn build_repo(yaml: &str) -> Result<Repository> {
+pub fn build_repo(yaml: &str) -> Result<Repository> {
     let f: YamlFile = serde_yaml::from_str(yaml)?;
     let mut repo = Repository::default();
 
     for command in f.commands.iter() {
         match command {
             Command::Commit(c) => {
+                repo.check_unused_commit_id(&c.name)?;
                 repo.commit(c.name.clone());
+                if let Some(note) = &c.note {
+                    repo.note(&c.name, note)?;
+                }
             }
             Command::SimpleCommit(name) => {
+                repo.check_unused_commit_id(name)?;
                 repo.commit(name.clone());
             }
             Command::Branch(b) => {
@@ -23,7 +28,15 @@ fn build_repo(yaml: &str) -> Result<Repository> {
                 }
             }
             Command::Merge(m) => {
-                repo.merge(m.commit_name.clone(), &m.branches);
+                repo.check_unused_commit_id(&m.commit_name)?;
+                repo.merge(m.commit_name.clone(), &m.branches)?;
+            }
+            Command::Group(g) => {
+                let selection = match &g.range {
+                    Some(range) => Highlight::Range(range.clone()),
+                    None => Highlight::Commits(g.commits.clone()),
+                };
+                repo.group(&g.name, &selection)?;
             }
 
         }
@@ -34,8 +47,6 @@ fn build_repo(yaml: &str) -> Result<Repository> {
 
 #[cfg(test)]
 mod test {
-    use super::*;
-
     use crate::parser::yaml::repo_builder::build_repo;
 
     #[test]
@@ -46,10 +57,67 @@ mod test {
         ";
 
         // Act
-        let repo = build_repo(&yaml).unwrap();
+        let repo = build_repo(yaml).unwrap();
 
         // Test
         assert_eq!(repo.branch_iter().count(), 1);
         assert_eq!(repo.branch_commits("main").len(), 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn notes() {
+        // Setup
+        let yaml = "
+        commands:
+          - A
+          - name: B
+            note: the bug was introduced here
+        ";
+
+        // Act
+        let repo = build_repo(yaml).unwrap();
+
+        // Test
+        assert_eq!(repo.commits["B"].notes, vec!["the bug was introduced here"]);
+        assert!(repo.commits["A"].notes.is_empty());
+    }
+
+    #[test]
+    fn groups() {
+        // Setup
+        let yaml = "
+        commands:
+          - A
+          - B
+          - group: Sprint 12
+            commits: [A, B]
+          - branch: feature
+          - C
+          - group: 'PR #42'
+            range: main..feature
+        ";
+
+        // Act
+        let repo = build_repo(yaml).unwrap();
+
+        // Test
+        assert_eq!(repo.groups.len(), 2);
+        assert_eq!(repo.groups[0].commits, vec!["A", "B"]);
+        assert_eq!(repo.groups[1].name, "PR #42");
+        assert_eq!(repo.groups[1].commits, vec!["C"]);
+    }
+
+    #[test]
+    fn reused_commit_id() {
+        // Setup
+        let yaml = "
+        commands: ['A', 'A']
+        ";
+
+        // Act
+        let res = build_repo(yaml);
+
+        // Test
+        assert_eq!(res.err().unwrap().to_string(), "commit `A` already exists");
+    }
+}
@@ -1,4 +1,4 @@
 pub mod errors;
 pub mod pikchr;
 
-pub use pikchr::print_pikchr;
+pub use pikchr::{print_pikchr, print_pikchr_diagram, print_pikchr_diff, print_pikchr_layout};
@@ -1,2 +1,2 @@
 pub mod printer;
-pub use printer::print_pikchr;
+pub use printer::{print_pikchr, print_pikchr_diagram, print_pikchr_diff, print_pikchr_layout};
@@ -1,106 +1,323 @@
 use super::super::errors::*;
+use crate::geometry::layout::LabelKind;
+use crate::geometry::legend::{Symbol, TitleKind};
+use crate::geometry::{Layout, Point};
+use crate::model::{
+    Diagram, Repository, STYLE_COLLAPSED, STYLE_ELIDED, STYLE_FADED, STYLE_HIGHLIGHT, STYLE_MOVED,
+};
+use crate::options::layout::LayoutOptions;
 use crate::view::View;
-use crate::options::layout::{LayoutDirection, LayoutOptions};
 use std::io::{BufWriter, Write};
 
+fn pikchr_number(v: f64) -> f64 {
+    // Round away floating point noise
+    (v * 1000.0).round() / 1000.0
+}
+
+fn pikchr_point(p: &Point) -> String {
+    format!("({}cm, {}cm)", pikchr_number(p.x), pikchr_number(p.y))
+}
+
+// A text in quotes, as pikchr strings
+fn pikchr_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\\\""))
+}
+
+fn pikchr_path(points: &[Point]) -> String {
+    points
+        .iter()
+        .map(pikchr_point)
+        .collect::<Vec<String>>()
+        .join(" then to ")
+}
+
+// Pikchr attributes of the commit styles
+fn node_attributes(style: &str) -> &'static str {
+    match style {
+        STYLE_FADED => " color gray",
+        STYLE_HIGHLIGHT => " thick fill lightyellow",
+        STYLE_COLLAPSED => " dashed",
+        _ => "",
+    }
+}
+
+// Pikchr attributes of the connector styles
+fn edge_attributes(style: &str) -> &'static str {
+    match style {
+        STYLE_FADED => " color gray",
+        STYLE_HIGHLIGHT => " thick",
+        STYLE_MOVED => " dashed",
+        STYLE_ELIDED => " dashed",
+        _ => "",
+    }
+}
+
+// Pikchr attributes of the text and the box of the label kinds
+fn label_attributes(kind: &LabelKind) -> (&'static str, &'static str) {
+    match kind {
+        LabelKind::Branch => ("", ""),
+        LabelKind::CheckedOut => (" bold", " thick"),
+        LabelKind::Head => (" bold", " rad 0.1cm fill lightgray"),
+        LabelKind::Remote => (" italic", " dashed"),
+        LabelKind::Tag => ("", " dotted fill lightyellow"),
+        LabelKind::Collapsed => (" italic", " invis"),
+    }
+}
+
 pub fn print_pikchr(view: &View, options: &LayoutOptions) -> Result<String> {
+    print_pikchr_layout(&Layout::from_view(view, options))
+}
+
+pub fn print_pikchr_diagram(diagram: &Diagram, options: &LayoutOptions) -> Result<String> {
+    print_pikchr_layout(&Layout::from_diagram(diagram, options))
+}
+
+// Two states of a repository in one diagram, see View::from_diff
+pub fn print_pikchr_diff(
+    before: &Repository,
+    after: &Repository,
+    options: &LayoutOptions,
+) -> Result<String> {
+    print_pikchr(&View::from_diff(before, after, options), options)
+}
+
+pub fn print_pikchr_layout(layout: &Layout) -> Result<String> {
     // Output buffer
     let mut buf = BufWriter::new(Vec::new());
-    // Start with the direction
-    match options.graph_direction {
-        LayoutDirection::UP => writeln!(buf, "down").map_err(|e| e.to_string())?,
-        LayoutDirection::RIGHT => {}
+
+    // The repositories, drawn first to stay behind the graphs
+    if !layout.swimlanes.is_empty() {
+        writeln!(buf, "// repositories")?;
+    }
+    for swimlane in &layout.swimlanes {
+        writeln!(
+            buf,
+            "box at {} wid {}cm ht {}cm rad 0.2cm",
+            pikchr_point(&swimlane.rect.center()),
+            pikchr_number(swimlane.rect.width),
+            pikchr_number(swimlane.rect.height)
+        )?;
+        writeln!(
+            buf,
+            "text {} bold at {}",
+            pikchr_string(&swimlane.title),
+            pikchr_point(&swimlane.title_rect.center())
+        )?;
     }
 
-    // Options
-    writeln!(buf, "circlerad = {}cm", options.commit_radius)?;
+    // The groups, drawn before the commits to stay behind them
+    if !layout.groups.is_empty() {
+        writeln!(buf, "// groups")?;
+    }
+    for group in &layout.groups {
+        writeln!(
+            buf,
+            "box at {} wid {}cm ht {}cm rad 0.3cm fill aliceblue",
+            pikchr_point(&group.rect.center()),
+            pikchr_number(group.rect.width),
+            pikchr_number(group.rect.height)
+        )?;
+        writeln!(
+            buf,
+            "text {} italic at {}",
+            pikchr_string(&group.name),
+            pikchr_point(&group.label.center())
+        )?;
+    }
 
-    // Go through the lanes and paint them
-    for lane in &view.lanes {
-        writeln!(buf, "// branch: {}", lane.branch_names.join(", "))?;
-        // First one
-        if let Some(first_commit) = lane.commits.first() {
-            // absolute position the commit
-            writeln!(
-                buf,
-                "circle \"{}\" at ({}cm, {}cm)",
-                first_commit.id,
-                lane.col * options.branch_dist,
-                first_commit.time * options.commit_hist_dist
-            )?;
-            let mut last_commit = first_commit;
-            // Go through the remaining commits
-            for commit in lane.commits.iter().skip(1) {
-                let hist_diff = last_commit.time - commit.time;
-                // Draw the arrow
-                writeln!(
-                    buf,
-                    "arrow {}cm chop",
-                    hist_diff * options.commit_hist_dist - 2 * options.commit_radius
-                )?;
-                // Draw the new commit
-                writeln!(buf, "circle \"{}\"", commit.id)?;
-                last_commit = commit;
-            }
-        }
+    // The commits
+    writeln!(buf, "// commits")?;
+    for node in &layout.nodes {
+        writeln!(
+            buf,
+            "circle {} at {} rad {}cm{}",
+            pikchr_string(&node.label),
+            pikchr_point(&node.center),
+            pikchr_number(node.radius),
+            node_attributes(&node.style)
+        )?;
     }
 
-    // Go through commits and print those lines, that are not "in-lane"
-    writeln!(buf, "// out of branch parents")?;
-    for (commit_id, commit) in &view.commits {
-        for parent in commit.parents.iter() {
-            if parent.in_lane {
-                continue;
-            }
-            match (parent.begins_lane, parent.ends_lane) {
-                (true, false) => {
-                    let down_dist = (commit.time - parent.commit.time) * (options.commit_hist_dist)
-                        - (options.branch_dist);
-                    writeln!(
-                        buf,
-                        "arrow from {} chop then to {}cm below {} then to {} chop",
-                        commit_id, down_dist, commit_id, parent.commit.id
-                    )?;
-                }
-                (false, true) => {
-                    let up_dist = (commit.time - parent.commit.time) * (options.commit_hist_dist)
-                        - (options.branch_dist);
-                    writeln!(
-                        buf,
-                        "arrow from {} chop then to {}cm above {} then to {} chop",
-                        commit_id, up_dist, parent.commit.id, parent.commit.id
-                    )?;
-                }
-                _ => {
-                    writeln!(buf, "arrow from {} to {} chop", commit_id, parent.commit.id)?;
-                }
-            }
+    // The connectors to the parents
+    writeln!(buf, "// parents")?;
+    for edge in &layout.edges {
+        writeln!(
+            buf,
+            "arrow from {}{}",
+            pikchr_path(&edge.points),
+            edge_attributes(&edge.style)
+        )?;
+        // Left out history ends in dots
+        if let (STYLE_ELIDED, Some(end)) = (edge.style.as_str(), edge.points.last()) {
+            writeln!(buf, "text \"...\" at {} below", pikchr_point(end))?;
         }
     }
 
-    // Prepare branch tips
+    // Branch tips!
     writeln!(buf, "// branch heads")?;
-    writeln!(buf, "boxht = 0;")?;
+    for label in &layout.labels {
+        if !label.connector.is_empty() {
+            let line = match label.kind {
+                LabelKind::Head => "arrow",
+                _ => "line",
+            };
+            writeln!(buf, "{} from {}", line, pikchr_path(&label.connector))?;
+        }
+        let (text_attributes, box_attributes) = label_attributes(&label.kind);
+        writeln!(
+            buf,
+            "box {}{} at {} wid {}cm ht {}cm{}",
+            pikchr_string(&label.text),
+            text_attributes,
+            pikchr_point(&label.rect.center()),
+            pikchr_number(label.rect.width),
+            pikchr_number(label.rect.height),
+            box_attributes
+        )?;
+    }
 
-    // Branch tips!
-    for (commit, branches) in &view.commits_branch_heads {
-        if let Some(branch) = branches.first() {
-            // First branch
-            writeln!(buf, "right")?;
+    // Brackets of squashed commits
+    if !layout.brackets.is_empty() {
+        writeln!(buf, "// squashed commits")?;
+    }
+    for bracket in &layout.brackets {
+        writeln!(buf, "line from {}", pikchr_path(&bracket.points))?;
+        writeln!(
+            buf,
+            "text {} italic at {}",
+            pikchr_string(&bracket.text),
+            pikchr_point(&bracket.label.center())
+        )?;
+    }
+
+    // Notes on commits
+    if !layout.callouts.is_empty() {
+        writeln!(buf, "// notes")?;
+    }
+    for callout in &layout.callouts {
+        writeln!(buf, "line from {} dotted", pikchr_path(&callout.leader))?;
+        writeln!(
+            buf,
+            "box {} at {} wid {}cm ht {}cm rad 0.2cm fill lightyellow",
+            pikchr_string(&callout.text),
+            pikchr_point(&callout.rect.center()),
+            pikchr_number(callout.rect.width),
+            pikchr_number(callout.rect.height)
+        )?;
+    }
+
+    // The legend, with a sample of every symbol
+    if let Some(legend) = &layout.legend {
+        writeln!(buf, "// legend")?;
+        writeln!(
+            buf,
+            "box at {} wid {}cm ht {}cm",
+            pikchr_point(&legend.rect.center()),
+            pikchr_number(legend.rect.width),
+            pikchr_number(legend.rect.height)
+        )?;
+        for entry in &legend.entries {
+            let sample = &entry.sample;
+            let center = sample.center();
+            // Shapes are a bit smaller than the sample, to keep the entries apart
+            let height = sample.height * 0.8;
+            match &entry.symbol {
+                Symbol::Node(style) => writeln!(
+                    buf,
+                    "circle at {} rad {}cm{}",
+                    pikchr_point(&center),
+                    pikchr_number(height / 2.0),
+                    node_attributes(style)
+                )?,
+                Symbol::Edge(style) => writeln!(
+                    buf,
+                    "arrow from {} to {}{}",
+                    pikchr_point(&Point::new(sample.x, center.y)),
+                    pikchr_point(&Point::new(sample.right(), center.y)),
+                    edge_attributes(style)
+                )?,
+                Symbol::Label(kind) => writeln!(
+                    buf,
+                    "box at {} wid {}cm ht {}cm{}",
+                    pikchr_point(&center),
+                    pikchr_number(sample.width),
+                    pikchr_number(height),
+                    label_attributes(kind).1
+                )?,
+                Symbol::Callout => writeln!(
+                    buf,
+                    "box at {} wid {}cm ht {}cm rad 0.2cm fill lightyellow",
+                    pikchr_point(&center),
+                    pikchr_number(sample.width),
+                    pikchr_number(height)
+                )?,
+                Symbol::Bracket => {
+                    let (x, tick) = (center.x, sample.width / 4.0);
+                    let (bottom, top) = (center.y - height / 2.0, center.y + height / 2.0);
+                    let points = [
+                        Point::new(x + tick, bottom),
+                        Point::new(x, bottom),
+                        Point::new(x, top),
+                        Point::new(x + tick, top),
+                    ];
+                    writeln!(buf, "line from {}", pikchr_path(&points))?
+                }
+            }
             writeln!(
                 buf,
-                "line from {} to ({}cm, {}.y) chop",
-                commit,
-                view.lanes.len() * options.branch_dist,
-                commit
+                "text {} ljust at {}",
+                pikchr_string(&entry.text),
+                pikchr_point(&Point::new(entry.text_rect.x, entry.text_rect.center().y))
             )?;
-            writeln!(buf, "box \"{}\"", branch.name)?;
-        }
-        for branch in branches.iter().skip(1) {
-            // Other branches
-            writeln!(buf, "box \"{}\"", branch.name)?;
         }
     }
 
+    // Title, subtitle and caption
+    if !layout.titles.is_empty() {
+        writeln!(buf, "// titles")?;
+    }
+    for title in &layout.titles {
+        let attributes = match title.kind {
+            TitleKind::Title => " big bold",
+            TitleKind::Subtitle => "",
+            TitleKind::Caption => " small italic",
+        };
+        writeln!(
+            buf,
+            "text {}{} at {}",
+            pikchr_string(&title.text),
+            attributes,
+            pikchr_point(&title.rect.center())
+        )?;
+    }
+
     Ok(String::from_utf8(buf.into_inner()?)?)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    #[test]
+    fn quotes_in_texts() {
+        // Setup
+        let repo = parse_git_instructions(
+            r#"
+            checkout("x\"y")
+            commit("a\"b")
+            "#,
+        )
+        .unwrap();
+        let options = LayoutOptions::default();
+
+        // Act
+        let pikchr = print_pikchr(&View::from_state(&repo, &options), &options).unwrap();
+
+        // Test
+        assert!(pikchr.contains(r#"circle "a\"b" at"#));
+        assert!(pikchr.contains(r#"box "x\"y" bold at"#));
+        assert!(!pikchr.contains(r#""a"b""#));
+        assert!(!pikchr.contains(r#""x"y""#));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::model::Repository;
+use crate::options::layout::RowMode;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Rows for the commits of several repositories, so that commits
+// with the same id get the same row in all of them.
+// The histories are combined into one graph, in which every commit comes after its parents.
+pub fn aligned_rows(repositories: &[&Repository], mode: &RowMode) -> HashMap<String, usize> {
+    // The combined graph
+    let mut parents: HashMap<&String, Vec<&String>> = HashMap::new();
+    let mut times: HashMap<&String, usize> = HashMap::new();
+    for repo in repositories {
+        for commit in repo.commits.values() {
+            let commit_parents = parents.entry(&commit.id).or_default();
+            for parent in &commit.parents {
+                if !commit_parents.contains(&parent) {
+                    commit_parents.push(parent);
+                }
+            }
+            let time = times.entry(&commit.id).or_insert(commit.time);
+            *time = (*time).min(commit.time);
+        }
+    }
+    let mut children: HashMap<&String, Vec<&String>> = HashMap::new();
+    let mut missing_parents: HashMap<&String, usize> = HashMap::new();
+    for (&id, commit_parents) in &parents {
+        missing_parents.insert(id, commit_parents.len());
+        for &parent in commit_parents {
+            children.entry(parent).or_default().push(id);
+        }
+    }
+
+    // Go through the commits once all their parents are done,
+    // the earliest created first
+    let mut ready = missing_parents
+        .iter()
+        .filter(|(_, &missing)| missing == 0)
+        .map(|(&id, _)| Reverse((times[id], id)))
+        .collect::<BinaryHeap<Reverse<(usize, &String)>>>();
+    let mut rows: HashMap<String, usize> = HashMap::new();
+    while let Some(Reverse((_, id))) = ready.pop() {
+        let row = match mode {
+            RowMode::Chronological => rows.len(),
+            RowMode::Compact => parents[id].iter().map(|&p| rows[p] + 1).max().unwrap_or(0),
+        };
+        rows.insert(id.clone(), row);
+        for &child in children.get(id).into_iter().flatten() {
+            let missing = missing_parents.get_mut(child).unwrap();
+            *missing -= 1;
+            if *missing == 0 {
+                ready.push(Reverse((times[child], child)));
+            }
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn upstream_and_fork() -> (Repository, Repository) {
+        let mut upstream = Repository::default();
+        upstream.commit("A".to_string());
+        upstream.commit("B".to_string());
+        upstream.commit("C".to_string());
+        let mut fork = Repository::default();
+        fork.commit("A".to_string());
+        fork.commit("B".to_string());
+        fork.commit("X".to_string());
+        fork.commit("Y".to_string());
+        (upstream, fork)
+    }
+
+    #[test]
+    fn shared_commits_share_rows() {
+        // Setup
+        let (upstream, fork) = upstream_and_fork();
+
+        // Act
+        let rows = aligned_rows(&[&upstream, &fork], &RowMode::Compact);
+
+        // Test
+        assert_eq!(rows["A"], 0);
+        assert_eq!(rows["B"], 1);
+        assert_eq!(rows["C"], 2);
+        assert_eq!(rows["X"], 2);
+        assert_eq!(rows["Y"], 3);
+    }
+
+    #[test]
+    fn chronological() {
+        // Setup
+        let (upstream, fork) = upstream_and_fork();
+
+        // Act
+        let rows = aligned_rows(&[&upstream, &fork], &RowMode::Chronological);
+
+        // Test
+        assert_eq!(rows["B"], 1);
+        assert!(rows["C"] > rows["B"]);
+        assert!(rows["X"] > rows["B"]);
+        assert!(rows["Y"] > rows["X"]);
+        assert_eq!(rows.len(), 5);
+    }
+}
@@ -0,0 +1,146 @@
+use super::view::{Commit, View};
+use crate::model::Repository;
+use crate::options::layout::LayoutOptions;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+impl View {
+    // The state with the long runs of plain commits replaced by their latest commit,
+    // which gets the parents of the first one. Also returns the commits of each run,
+    // by the commit that stays.
+    pub(super) fn collapsed_runs(
+        state: &Repository,
+        options: &LayoutOptions,
+    ) -> Option<(Repository, HashMap<String, Vec<String>>)> {
+        let min_length = options.collapse_runs?.max(2);
+
+        // Commits that have to be shown on their own
+        let mut children: HashMap<&String, usize> = HashMap::new();
+        for commit in state.commit_iter() {
+            for parent in &commit.parents {
+                *children.entry(parent).or_default() += 1;
+            }
+        }
+        let mut visible = state
+            .branch_iter()
+            .filter_map(|b| b.current_commit.as_ref())
+            .chain(state.current_commit())
+            .chain(&options.visible_commits)
+            .collect::<HashSet<&String>>();
+        for commit in state.commit_iter() {
+            visible.extend(&commit.squashed);
+            if !commit.squashed.is_empty() {
+                visible.insert(&commit.id);
+            }
+        }
+        let plain = |id: &String| {
+            let commit = &state.commits[id];
+            !visible.contains(id)
+                && commit.parents.len() <= 1
+                && commit.elided_parents.is_empty()
+                && commit.tags.is_empty()
+                && commit.notes.is_empty()
+                && children.get(id).copied().unwrap_or(0) <= 1
+        };
+
+        // The commit continuing each commit in its lane
+        let mut next: HashMap<&String, &String> = HashMap::new();
+        for commit in state.commit_iter() {
+            if let Some(parent) = commit.parents.first() {
+                if state.commits[parent].branch == commit.branch {
+                    next.insert(parent, &commit.id);
+                }
+            }
+        }
+
+        // Follow each run from its first commit
+        let mut runs = HashMap::new();
+        for commit in state.date_order_iter() {
+            let continues_run = commit
+                .parents
+                .first()
+                .is_some_and(|p| plain(p) && next.get(p) == Some(&&commit.id));
+            if !plain(&commit.id) || continues_run {
+                continue;
+            }
+            let mut run = vec![commit.id.clone()];
+            while let Some(&child) = next.get(run.last().unwrap()).filter(|c| plain(c)) {
+                run.push(child.clone());
+            }
+            if run.len() >= min_length {
+                runs.insert(run.last().unwrap().clone(), run);
+            }
+        }
+        if runs.is_empty() {
+            return None;
+        }
+
+        let hidden = runs
+            .values()
+            .flat_map(|run| &run[..run.len() - 1])
+            .cloned()
+            .collect::<HashSet<String>>();
+        let mut collapsed = state.clone();
+        for (id, run) in &runs {
+            let parents = state.commits[&run[0]].parents.clone();
+            collapsed.commits.get_mut(id).unwrap().parents = parents;
+        }
+        Some((collapsed.without_commits(&hidden), runs))
+    }
+
+    // The node a commit is drawn as: the commit, or the run it has been collapsed into
+    pub fn shown_commit(&self, id: &String) -> Option<&Rc<Commit>> {
+        self.commits
+            .get(id)
+            .or_else(|| self.commits.values().find(|c| c.collapsed.contains(id)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_git_instructions;
+
+    #[test]
+    fn runs() {
+        // Setup
+        let mut repo = parse_git_instructions(
+            "
+            commit(A)
+            commit(B)
+            commit(C)
+            commit(D)
+            checkout(feature)
+            commit(F1)
+            checkout(main)
+            commit(E)
+            commit(G)
+            commit(H)
+            commit(I)
+            commit(J)
+            commit(K)
+            merge(M, feature)
+            ",
+        )
+        .unwrap();
+        repo.commits.get_mut("H").unwrap().tags = vec!["v1".to_string()];
+        let options = LayoutOptions {
+            collapse_runs: Some(3),
+            visible_commits: vec!["B".to_string()],
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let view = View::from_state(&repo, &options);
+
+        // Test
+        let mut ids = view.commits.keys().cloned().collect::<Vec<String>>();
+        ids.sort();
+        // B is kept visible, D has a branch-off, H a tag, so only I J K are a long run
+        assert_eq!(ids, vec!["A", "B", "C", "D", "E", "F1", "G", "H", "K", "M"]);
+        assert_eq!(view.commits["K"].collapsed, vec!["I", "J", "K"]);
+        assert!(view.commits["G"].collapsed.is_empty());
+        assert_eq!(view.commits["K"].parents[0].commit.id, "H");
+        assert_eq!(view.shown_commit(&"J".to_string()).unwrap().id, "K");
+    }
+}
@@ -0,0 +1,80 @@
+use super::align::aligned_rows;
+use super::view::{MovedRef, View};
+use crate::model::{Repository, RepositoryDiff, STYLE_FADED, STYLE_HIGHLIGHT};
+use crate::options::layout::{LayoutOptions, RowMode};
+
+impl View {
+    // Two states of a repository in one view. The removed commits are faded,
+    // the added ones highlighted, and the refs that moved point from where they were.
+    pub fn from_diff(before: &Repository, after: &Repository, options: &LayoutOptions) -> View {
+        let diff = RepositoryDiff::new(before, after);
+        let mut combined = after.clone();
+        for id in &diff.removed_commits {
+            let mut commit = before.commits[id].clone();
+            commit.style = STYLE_FADED.to_string();
+            combined.commits.insert(id.clone(), commit);
+        }
+        for id in &diff.added_commits {
+            combined.commits.get_mut(id).unwrap().style = STYLE_HIGHLIGHT.to_string();
+        }
+        // Both histories in one order of time
+        let times = aligned_rows(&[before, after], &RowMode::Chronological);
+        for commit in combined.commits.values_mut() {
+            commit.time = times[&commit.id];
+        }
+
+        let mut view = View::from_state(&combined, options);
+        let moved_refs = diff
+            .moved_refs
+            .iter()
+            .filter_map(|m| match (&m.before, &m.after) {
+                (Some(from), Some(to)) => Some(MovedRef {
+                    name: m.name.clone(),
+                    from: view.shown_commit(from)?.clone(),
+                    to: view.shown_commit(to)?.clone(),
+                }),
+                _ => None,
+            })
+            .collect::<Vec<MovedRef>>();
+        view.moved_refs = moved_refs;
+        view
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reset() {
+        // Setup
+        let mut before = Repository::default();
+        before.commit("A".to_string());
+        before.commit("B".to_string());
+        before.commit("C".to_string());
+        let mut after = Repository::default();
+        after.commit("A".to_string());
+        after.commit("B".to_string());
+        after.checkout_branch("feature");
+        after.commit("D".to_string());
+        let options = LayoutOptions::default();
+
+        // Act
+        let view = View::from_diff(&before, &after, &options);
+
+        // Test
+        assert_eq!(view.commits["C"].style, STYLE_FADED);
+        assert_eq!(view.commits["C"].parents[0].style, STYLE_FADED);
+        assert_eq!(view.commits["D"].style, STYLE_HIGHLIGHT);
+        assert_eq!(view.commits["B"].style, "");
+        // The removed commit still has a lane
+        assert!(view
+            .lanes
+            .iter()
+            .any(|l| l.commits.iter().any(|c| c.id == "C")));
+        assert_eq!(view.moved_refs.len(), 1);
+        assert_eq!(view.moved_refs[0].name, "main");
+        assert_eq!(view.moved_refs[0].from.id, "C");
+        assert_eq!(view.moved_refs[0].to.id, "B");
+    }
+}
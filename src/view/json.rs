@@ -0,0 +1,394 @@
+use super::view::{Branch, Commit, Group, Head, Lane, MovedRef, Parent, View};
+use crate::model;
+use crate::model::json::HeadJson;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// The JSON form of a view, commits are referenced by id. Commits are listed in time order,
+// so their parents and the commits they replace come before them.
+#[derive(Serialize, Deserialize)]
+struct ViewJson {
+    commits: Vec<CommitJson>,
+    lanes: Vec<LaneJson>,
+    branches: Vec<BranchJson>,
+    #[serde(default)]
+    head: Option<HeadJson>,
+    #[serde(default)]
+    moved_refs: Vec<MovedRefJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<model::Group>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CommitJson {
+    id: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    hash: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    parents: Vec<ParentJson>,
+    #[serde(default)]
+    style: String,
+    time: usize,
+    row: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    squashed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    elided_parents: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    collapsed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ParentJson {
+    commit: String,
+    #[serde(default)]
+    style: String,
+    ends_lane: bool,
+    begins_lane: bool,
+    in_lane: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LaneJson {
+    branch_names: Vec<String>,
+    commits: Vec<String>,
+    col: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BranchJson {
+    name: String,
+    #[serde(default)]
+    head: Option<String>,
+    #[serde(default)]
+    style: String,
+    priority: usize,
+    #[serde(default)]
+    checked_out: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MovedRefJson {
+    name: String,
+    from: String,
+    to: String,
+}
+
+fn ids(commits: &[Rc<Commit>]) -> Vec<String> {
+    commits.iter().map(|c| c.id.clone()).collect()
+}
+
+impl From<&View> for ViewJson {
+    fn from(view: &View) -> ViewJson {
+        let mut commits = view.commits.values().collect::<Vec<&Rc<Commit>>>();
+        commits.sort_by(|a, b| (a.time, &a.id).cmp(&(b.time, &b.id)));
+        let commits = commits
+            .into_iter()
+            .map(|c| CommitJson {
+                id: c.id.clone(),
+                message: c.message.clone(),
+                hash: c.hash.clone(),
+                tags: c.tags.clone(),
+                parents: c
+                    .parents
+                    .iter()
+                    .map(|p| ParentJson {
+                        commit: p.commit.id.clone(),
+                        style: p.style.clone(),
+                        ends_lane: p.ends_lane,
+                        begins_lane: p.begins_lane,
+                        in_lane: p.in_lane,
+                    })
+                    .collect(),
+                style: c.style.clone(),
+                time: c.time,
+                row: c.row,
+                squashed: ids(&c.squashed),
+                elided_parents: c.elided_parents.clone(),
+                collapsed: c.collapsed.clone(),
+                notes: c.notes.clone(),
+            })
+            .collect();
+        let lanes = view
+            .lanes
+            .iter()
+            .map(|l| LaneJson {
+                branch_names: l.branch_names.clone(),
+                commits: ids(&l.commits),
+                col: l.col,
+            })
+            .collect();
+        let mut branches = view.branches.values().collect::<Vec<&Rc<Branch>>>();
+        branches.sort_by(|a, b| (a.priority, &a.name).cmp(&(b.priority, &b.name)));
+        let branches = branches
+            .into_iter()
+            .map(|b| BranchJson {
+                name: b.name.clone(),
+                head: b.head.as_ref().map(|c| c.id.clone()),
+                style: b.style.clone(),
+                priority: b.priority,
+                checked_out: b.checked_out,
+                remote: b.remote.clone(),
+            })
+            .collect();
+        let head = view.head.as_ref().map(|h| match h {
+            Head::Branch(branch) => HeadJson::Branch(branch.name.clone()),
+            Head::Detached(commit) => HeadJson::Detached(commit.id.clone()),
+        });
+        let moved_refs = view
+            .moved_refs
+            .iter()
+            .map(|m| MovedRefJson {
+                name: m.name.clone(),
+                from: m.from.id.clone(),
+                to: m.to.id.clone(),
+            })
+            .collect();
+        let groups = view
+            .groups
+            .iter()
+            .map(|g| model::Group {
+                name: g.name.clone(),
+                commits: g.commits.iter().map(|c| c.id.clone()).collect(),
+            })
+            .collect();
+        ViewJson {
+            commits,
+            lanes,
+            branches,
+            head,
+            moved_refs,
+            groups,
+        }
+    }
+}
+
+impl TryFrom<ViewJson> for View {
+    type Error = String;
+
+    fn try_from(json: ViewJson) -> Result<View, String> {
+        let mut commits: HashMap<String, Rc<Commit>> = HashMap::new();
+        let commit = |commits: &HashMap<String, Rc<Commit>>, id: &String| {
+            commits
+                .get(id)
+                .cloned()
+                .ok_or_else(|| format!("unknown commit `{}`", id))
+        };
+        for c in json.commits {
+            if commits.contains_key(&c.id) {
+                return Err(format!("commit `{}` is listed twice", c.id));
+            }
+            // Parents and replaced commits are listed before
+            let parents = c
+                .parents
+                .into_iter()
+                .map(|p| {
+                    Ok(Parent {
+                        commit: commit(&commits, &p.commit)?,
+                        style: p.style,
+                        ends_lane: p.ends_lane,
+                        begins_lane: p.begins_lane,
+                        in_lane: p.in_lane,
+                    })
+                })
+                .collect::<Result<Vec<Parent>, String>>()?;
+            let squashed = c
+                .squashed
+                .iter()
+                .map(|id| commit(&commits, id))
+                .collect::<Result<Vec<Rc<Commit>>, String>>()?;
+            commits.insert(
+                c.id.clone(),
+                Rc::new(Commit {
+                    id: c.id,
+                    message: c.message,
+                    hash: c.hash,
+                    tags: c.tags,
+                    parents,
+                    style: c.style,
+                    time: c.time,
+                    row: c.row,
+                    squashed,
+                    elided_parents: c.elided_parents,
+                    collapsed: c.collapsed,
+                    notes: c.notes,
+                }),
+            );
+        }
+
+        let lanes = json
+            .lanes
+            .into_iter()
+            .map(|l| {
+                Ok(Rc::new(Lane {
+                    branch_names: l.branch_names,
+                    commits: l
+                        .commits
+                        .iter()
+                        .map(|id| commit(&commits, id))
+                        .collect::<Result<Vec<Rc<Commit>>, String>>()?,
+                    col: l.col,
+                }))
+            })
+            .collect::<Result<Vec<Rc<Lane>>, String>>()?;
+
+        let mut branches = HashMap::new();
+        for b in json.branches {
+            let head = match &b.head {
+                Some(id) => Some(commit(&commits, id)?),
+                None => None,
+            };
+            branches.insert(
+                b.name.clone(),
+                Rc::new(Branch {
+                    name: b.name,
+                    head,
+                    style: b.style,
+                    priority: b.priority,
+                    checked_out: b.checked_out,
+                    remote: b.remote,
+                }),
+            );
+        }
+
+        let head = match json.head {
+            Some(HeadJson::Branch(name)) => Some(Head::Branch(
+                branches
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| format!("unknown branch `{}`", name))?,
+            )),
+            Some(HeadJson::Detached(id)) => Some(Head::Detached(commit(&commits, &id)?)),
+            None => None,
+        };
+        let moved_refs = json
+            .moved_refs
+            .into_iter()
+            .map(|m| {
+                Ok(MovedRef {
+                    from: commit(&commits, &m.from)?,
+                    to: commit(&commits, &m.to)?,
+                    name: m.name,
+                })
+            })
+            .collect::<Result<Vec<MovedRef>, String>>()?;
+        let groups = json
+            .groups
+            .into_iter()
+            .map(|g| {
+                Ok(Group {
+                    commits: g
+                        .commits
+                        .iter()
+                        .map(|id| commit(&commits, id))
+                        .collect::<Result<Vec<Rc<Commit>>, String>>()?,
+                    name: g.name,
+                })
+            })
+            .collect::<Result<Vec<Group>, String>>()?;
+
+        Ok(View {
+            commits_branch_heads: View::branch_heads(&branches),
+            commits,
+            lanes,
+            branches,
+            head,
+            moved_refs,
+            groups,
+        })
+    }
+}
+
+impl Serialize for View {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ViewJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for View {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<View, D::Error> {
+        View::try_from(ViewJson::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+impl View {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<View> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::Repository;
+    use crate::options::layout::LayoutOptions;
+    use crate::parse_git_instructions;
+
+    #[test]
+    fn round_trip() {
+        // Setup
+        let repo = parse_git_instructions(
+            "
+            commit(A)
+            checkout(feature)
+            commit(B)
+            checkout(main)
+            commit(C)
+            merge(M, feature)
+            ",
+        )
+        .unwrap();
+        let view = View::from_state(&repo, &LayoutOptions::default());
+
+        // Act
+        let json = view.to_json().unwrap();
+        let loaded = View::from_json(&json).unwrap();
+
+        // Test
+        assert_eq!(loaded.to_json().unwrap(), json);
+        assert!(Rc::ptr_eq(
+            &loaded.commits["M"].parents[1].commit,
+            &loaded.commits["B"]
+        ));
+        assert!(Rc::ptr_eq(
+            loaded.branches["main"].head.as_ref().unwrap(),
+            &loaded.commits["M"]
+        ));
+        assert_eq!(loaded.commits_branch_heads["M"][0].name, "main");
+        assert!(matches!(&loaded.head, Some(Head::Branch(b)) if b.name == "main"));
+    }
+
+    #[test]
+    fn unknown_commit() {
+        // Setup
+        let view = View::from_state(
+            &Repository::from_json(r#"{"commits": [], "branches": []}"#).unwrap(),
+            &LayoutOptions::default(),
+        );
+        let mut json = serde_json::to_value(&view).unwrap();
+        json["branches"] = serde_json::json!([{"name": "main", "head": "A", "priority": 0}]);
+
+        // Act
+        let res = serde_json::from_value::<View>(json);
+
+        // Test
+        assert!(res
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("unknown commit `A`"));
+    }
+}
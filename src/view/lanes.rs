@@ -0,0 +1,331 @@
+use crate::options::layout::{BranchOrder, LaneAllocation, LayoutOptions};
+use std::collections::{HashMap, HashSet};
+
+// What the column allocation needs to know about a lane
+pub(crate) struct LaneSpan {
+    pub branch_names: Vec<String>,
+    pub priority: usize,
+    // Lanes of a lower group are placed left of lanes of a higher group
+    pub group: usize,
+    // First and last row the lane occupies in its column,
+    // including the connectors to the commits it branches off from and merges into.
+    // None if the lane has no commits.
+    pub rows: Option<(usize, usize)>,
+    // The lane this lane branches off from
+    pub source_lane: Option<usize>,
+}
+
+fn overlaps(a: (usize, usize), b: (usize, usize)) -> bool {
+    a.0 <= b.1 && b.0 <= a.1
+}
+
+// Assign a column to every lane.
+// Pinned branches always get their configured column, which is never given to another lane.
+pub(crate) fn allocate_columns(spans: &[LaneSpan], options: &LayoutOptions) -> Vec<usize> {
+    let mut columns: Vec<Option<usize>> = vec![None; spans.len()];
+    for (index, span) in spans.iter().enumerate() {
+        columns[index] = span
+            .branch_names
+            .iter()
+            .find_map(|name| options.pinned_lanes.get(name).cloned());
+    }
+    let reserved: HashSet<usize> = columns.iter().flatten().cloned().collect();
+
+    let mut order: Vec<usize> = (0..spans.len()).filter(|&i| columns[i].is_none()).collect();
+    match options.lane_allocation {
+        LaneAllocation::PerBranch => {
+            order.sort_by_key(|&i| spans[i].priority);
+            let mut free = (0..).filter(|c| !reserved.contains(c));
+            for i in order {
+                columns[i] = free.next();
+            }
+        }
+        LaneAllocation::Reuse => {
+            // Going through the lanes from top to bottom of the history
+            // allows a lane to take over the column of a lane that has already ended
+            order.sort_by_key(|&i| {
+                (
+                    spans[i].group,
+                    spans[i].rows.map(|r| r.0),
+                    spans[i].priority,
+                )
+            });
+            let max_column = spans.len() + reserved.iter().max().cloned().unwrap_or(0);
+            // Every group gets its own band of columns, right of the previous groups
+            let mut group = 0;
+            let mut band_start = 0;
+            let mut band_end = None;
+            for i in order {
+                if spans[i].group != group {
+                    group = spans[i].group;
+                    band_start = band_end.map(|c| c + 1).unwrap_or(band_start);
+                }
+                // Staying close to the lane we branch off from keeps connectors short,
+                // so they cross less other lanes
+                let target = spans[i]
+                    .source_lane
+                    .and_then(|s| columns[s])
+                    .unwrap_or(0)
+                    .max(band_start);
+                let column = (band_start..=max_column + band_start)
+                    .filter(|c| !reserved.contains(c))
+                    .filter(|&c| match spans[i].rows {
+                        None => true,
+                        Some(rows) => !spans.iter().enumerate().any(|(j, other)| {
+                            columns[j] == Some(c)
+                                && other.rows.map(|o| overlaps(o, rows)).unwrap_or(false)
+                        }),
+                    })
+                    .min_by_key(|&c| (c.abs_diff(target), c < target))
+                    .unwrap();
+                columns[i] = Some(column);
+                band_end = band_end.max(Some(column));
+            }
+        }
+    }
+    let columns = columns.into_iter().map(|c| c.unwrap()).collect();
+    match &options.branch_order {
+        BranchOrder::CenterMain(main) => center_columns(columns, spans, main, &reserved),
+        _ => columns,
+    }
+}
+
+// Move the column of the main branch into the center and alternate the other columns
+// to the right and the left of it, in the order they were allocated.
+// Pinned lanes keep their columns, and no other lane is moved onto them.
+fn center_columns(
+    columns: Vec<usize>,
+    spans: &[LaneSpan],
+    main: &str,
+    reserved: &HashSet<usize>,
+) -> Vec<usize> {
+    let pinned = |i: usize| reserved.contains(&columns[i]);
+    let main_column = match spans
+        .iter()
+        .position(|s| s.branch_names.iter().any(|n| n == main))
+    {
+        Some(i) => columns[i],
+        None => return columns,
+    };
+    let mut others = (0..columns.len())
+        .filter(|&i| !pinned(i) && columns[i] != main_column)
+        .map(|i| columns[i])
+        .collect::<Vec<usize>>();
+    others.sort();
+    others.dedup();
+    // A pinned main branch stays where it is, otherwise it gets the column
+    // that leaves room for half of the other columns on its left
+    let center = if reserved.contains(&main_column) {
+        main_column
+    } else {
+        (0..)
+            .filter(|c| !reserved.contains(c))
+            .nth(others.len() / 2)
+            .unwrap()
+    };
+    let mut left = (0..center).rev().filter(|c| !reserved.contains(c));
+    let mut right = (center + 1..).filter(|c| !reserved.contains(c));
+    let mut moved = HashMap::from([(main_column, center)]);
+    for (index, &column) in others.iter().enumerate() {
+        let target = match index % 2 {
+            0 => right.next(),
+            _ => left.next().or_else(|| right.next()),
+        };
+        moved.insert(column, target.unwrap());
+    }
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| if pinned(i) { c } else { moved[&c] })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn span(name: &str, priority: usize, rows: (usize, usize), source: Option<usize>) -> LaneSpan {
+        LaneSpan {
+            branch_names: vec![name.to_string()],
+            priority,
+            group: 0,
+            rows: Some(rows),
+            source_lane: source,
+        }
+    }
+
+    #[test]
+    fn reuse_ended_lane() {
+        // Setup
+        let spans = vec![
+            span("main", 0, (0, 10), None),
+            span("feature1", 1, (1, 3), Some(0)),
+            span("feature2", 2, (5, 8), Some(0)),
+        ];
+        let options = LayoutOptions {
+            lane_allocation: LaneAllocation::Reuse,
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![0, 1, 1]);
+    }
+
+    #[test]
+    fn overlapping_lanes() {
+        // Setup
+        let spans = vec![
+            span("main", 0, (0, 10), None),
+            span("feature1", 1, (1, 6), Some(0)),
+            span("feature2", 2, (5, 8), Some(0)),
+        ];
+        let options = LayoutOptions {
+            lane_allocation: LaneAllocation::Reuse,
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn pinned_lanes() {
+        // Setup
+        let spans = vec![
+            span("feature", 0, (0, 3), None),
+            span("main", 1, (0, 10), None),
+            span("develop", 2, (1, 10), Some(1)),
+        ];
+        let mut options = LayoutOptions::default();
+        options.pinned_lanes.insert("main".to_string(), 0);
+        options.pinned_lanes.insert("develop".to_string(), 1);
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn per_branch() {
+        // Setup
+        let spans = vec![
+            span("main", 0, (0, 10), None),
+            span("feature1", 1, (1, 3), Some(0)),
+            span("feature2", 2, (5, 8), Some(0)),
+        ];
+        let options = LayoutOptions {
+            lane_allocation: LaneAllocation::PerBranch,
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn groups_get_own_columns() {
+        // Setup
+        let mut spans = vec![
+            span("main", 0, (0, 10), None),
+            span("feature/a", 1, (1, 3), Some(0)),
+            span("release/1", 2, (2, 4), Some(0)),
+            span("feature/b", 3, (5, 6), Some(0)),
+        ];
+        let options = LayoutOptions {
+            lane_allocation: LaneAllocation::Reuse,
+            branch_order: BranchOrder::Groups(vec![
+                "main".to_string(),
+                "release/*".to_string(),
+                "feature/*".to_string(),
+            ]),
+            ..LayoutOptions::default()
+        };
+        for s in spans.iter_mut() {
+            s.group = options.branch_order.group(&s.branch_names[0]);
+        }
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![0, 2, 1, 2]);
+    }
+
+    #[test]
+    fn main_in_center() {
+        // Setup
+        let mut spans = vec![
+            span("main", 0, (0, 10), None),
+            span("feature1", 1, (1, 6), Some(0)),
+            span("feature2", 2, (2, 7), Some(0)),
+            span("feature3", 3, (3, 8), Some(0)),
+        ];
+        let options = LayoutOptions {
+            branch_order: BranchOrder::CenterMain("main".to_string()),
+            ..LayoutOptions::default()
+        };
+        for s in spans.iter_mut() {
+            s.group = options.branch_order.group(&s.branch_names[0]);
+        }
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn main_in_center_with_pinned_lanes() {
+        // Setup
+        let spans = vec![
+            span("main", 0, (0, 10), None),
+            span("feature1", 1, (1, 6), Some(0)),
+            span("feature2", 2, (2, 7), Some(0)),
+            span("release", 3, (3, 8), Some(0)),
+        ];
+        let mut options = LayoutOptions {
+            branch_order: BranchOrder::CenterMain("main".to_string()),
+            ..LayoutOptions::default()
+        };
+        options.pinned_lanes.insert("release".to_string(), 0);
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn pinned_main_in_center() {
+        // Setup
+        let spans = vec![
+            span("main", 0, (0, 10), None),
+            span("feature1", 1, (1, 6), Some(0)),
+            span("feature2", 2, (2, 7), Some(0)),
+        ];
+        let mut options = LayoutOptions {
+            branch_order: BranchOrder::CenterMain("main".to_string()),
+            ..LayoutOptions::default()
+        };
+        options.pinned_lanes.insert("main".to_string(), 3);
+
+        // Act
+        let columns = allocate_columns(&spans, &options);
+
+        // Test
+        assert_eq!(columns, vec![3, 4, 2]);
+    }
+}
@@ -1,2 +1,9 @@
+#[allow(clippy::module_inception)]
 mod view;
-pub use view::View;
\ No newline at end of file
+mod align;
+mod collapse;
+mod diff;
+mod json;
+mod lanes;
+pub use align::aligned_rows;
+pub use view::{Branch, Commit, Group, Head, Lane, MovedRef, Parent, View};
@@ -1,4 +1,8 @@
+use crate::model;
 use crate::model::repo::Repository;
+use crate::model::DETACHED_HEAD;
+use crate::options::layout::{LayoutOptions, RemoteRefs, RowMode, SquashDisplay};
+use crate::view::lanes::{allocate_columns, LaneSpan};
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
@@ -6,9 +10,20 @@ pub struct Commit {
     pub id: String,
     pub message: String,
     pub hash: String,
+    pub tags: Vec<String>,
     pub parents: Vec<Parent>,
     pub style: String,
     pub time: usize,
+    pub row: usize,
+    // The shown commits this one replaces
+    pub squashed: Vec<Rc<Commit>>,
+    // The parents that have been left out of the repository
+    pub elided_parents: Vec<String>,
+    // The commits of the run this commit stands for, including itself,
+    // empty if it stands for itself only
+    pub collapsed: Vec<String>,
+    // Remarks drawn as callouts next to the commit
+    pub notes: Vec<String>,
 }
 
 pub struct Parent {
@@ -23,6 +38,30 @@ pub struct Branch {
     pub name: String,
     pub head: Option<Rc<Commit>>,
     pub style: String,
+    // Position of the branch in the configured branch order
+    pub priority: usize,
+    pub checked_out: bool,
+    // The remote of a remote-tracking branch
+    pub remote: Option<String>,
+}
+
+// What is checked out
+pub enum Head {
+    Branch(Rc<Branch>),
+    Detached(Rc<Commit>),
+}
+
+// A ref that points to another commit than before, see View::from_diff
+pub struct MovedRef {
+    pub name: String,
+    pub from: Rc<Commit>,
+    pub to: Rc<Commit>,
+}
+
+// Commits drawn in a labeled box, see model::Group
+pub struct Group {
+    pub name: String,
+    pub commits: Vec<Rc<Commit>>,
 }
 
 pub struct Lane {
@@ -44,10 +83,54 @@ pub struct View {
     pub lanes: Vec<Rc<Lane>>,
     pub branches: HashMap<String, Rc<Branch>>,
     pub commits_branch_heads: HashMap<String, Vec<Rc<Branch>>>,
+    pub head: Option<Head>,
+    pub moved_refs: Vec<MovedRef>,
+    pub groups: Vec<Group>,
 }
 
 impl View {
-    pub fn from_state(state: &Repository) -> View {
+    pub fn from_state(state: &Repository, options: &LayoutOptions) -> View {
+        View::build(state, options, None)
+    }
+
+    // Like from_state, but with the rows of the commits given,
+    // e.g. to align the commits of several views
+    pub fn from_state_with_rows(
+        state: &Repository,
+        options: &LayoutOptions,
+        rows: &HashMap<String, usize>,
+    ) -> View {
+        View::build(state, options, Some(rows))
+    }
+
+    fn build(
+        state: &Repository,
+        options: &LayoutOptions,
+        rows: Option<&HashMap<String, usize>>,
+    ) -> View {
+        // Collapsed squashes hide the replaced commits no branch leads to anymore
+        let without_squashed;
+        let state = match options.squash_display {
+            SquashDisplay::Collapsed => {
+                let hidden = View::hidden_squashed(state);
+                if hidden.is_empty() {
+                    state
+                } else {
+                    without_squashed = state.without_commits(&hidden);
+                    &without_squashed
+                }
+            }
+            SquashDisplay::Expanded => state,
+        };
+        // Long runs of plain commits are drawn as one node
+        let without_runs;
+        let (state, runs) = match View::collapsed_runs(state, options) {
+            Some((collapsed, runs)) => {
+                without_runs = collapsed;
+                (&without_runs, runs)
+            }
+            None => (state, HashMap::new()),
+        };
         // Time list of commit ids
         // We need this, because if we create commits like this
         // we ensure that the parents have already been created.
@@ -56,27 +139,79 @@ impl View {
         for (id, commit) in state.commits.iter() {
             time_ord_commits[commit.time] = id;
         }
+        // Order the branches, the position in this order is the priority in the view
+        let mut ordered_branches = state.branches.values().collect::<Vec<&model::Branch>>();
+        ordered_branches.sort_by_key(|b| View::order_key(state, b, options));
+        let priorities = ordered_branches
+            .iter()
+            .enumerate()
+            .map(|(priority, b)| (b.name.clone(), priority))
+            .collect::<HashMap<String, usize>>();
         // Make temporary lanes, which only use commit ids
         // because the commits have not been created yet
         let mut commit_id_lanes: Vec<LaneWithCommitIds> = Vec::new();
-        let mut lane_index_for_commit: HashMap<String, usize> = HashMap::new();
-        for branch in state.branches.values() {
-            let mut head = state.branch_head(&branch.name);
-            let mut lane_commits: Vec<&String> = Vec::new();
-            while let Some(commit_id) = head {
-                let state_commit = state.commits.get(commit_id).unwrap();
-                if state_commit.branch == branch.name {
-                    lane_commits.push(commit_id);
-                    lane_index_for_commit.insert(commit_id.clone(), commit_id_lanes.len());
-                }
-                head = state_commit.parents.first();
-            }
+        for &branch in ordered_branches.iter() {
             commit_id_lanes.push(LaneWithCommitIds {
                 branch_names: Vec::from([branch.name.clone()]),
-                commit_ids: lane_commits,
-                priority: branch.priority,
+                commit_ids: View::lane_commit_ids(
+                    state,
+                    state.branch_head(&branch.name),
+                    &branch.name,
+                ),
+                priority: priorities[&branch.name],
+            });
+        }
+        // Commits made on a detached HEAD get a lane without a branch
+        if let Some(model::Head::Detached(commit)) = &state.head {
+            commit_id_lanes.push(LaneWithCommitIds {
+                branch_names: Vec::new(),
+                commit_ids: View::lane_commit_ids(state, Some(commit), DETACHED_HEAD),
+                priority: ordered_branches.len(),
+            });
+        }
+        // Commits that no branch reaches along its first parents, like fetched commits
+        // that were merged or the ones left behind by a rebase, get lanes without a branch
+        let mut in_lanes = commit_id_lanes
+            .iter()
+            .flat_map(|l| l.commit_ids.iter().cloned())
+            .collect::<HashSet<&String>>();
+        for &id in time_ord_commits.iter().rev() {
+            if in_lanes.contains(id) {
+                continue;
+            }
+            let commit_ids = View::lane_commit_ids(state, Some(id), &state.commits[id].branch)
+                .into_iter()
+                .filter(|c| !in_lanes.contains(c))
+                .collect::<Vec<&String>>();
+            in_lanes.extend(commit_ids.iter().cloned());
+            commit_id_lanes.push(LaneWithCommitIds {
+                branch_names: Vec::new(),
+                commit_ids,
+                priority: ordered_branches.len() + 1,
             });
         }
+        // Branches without commits of their own point into the history of another lane,
+        // they share that lane instead of getting an empty one
+        let (mut commit_id_lanes, empty_lanes): (Vec<LaneWithCommitIds>, Vec<LaneWithCommitIds>) =
+            commit_id_lanes
+                .into_iter()
+                .partition(|l| !l.commit_ids.is_empty());
+        let mut lane_index_for_commit: HashMap<String, usize> = HashMap::new();
+        for (index, lane) in commit_id_lanes.iter().enumerate() {
+            for &id in lane.commit_ids.iter() {
+                lane_index_for_commit.insert(id.clone(), index);
+            }
+        }
+        for lane in empty_lanes {
+            for name in lane.branch_names {
+                if let Some(&index) = state
+                    .branch_head(&name)
+                    .and_then(|head| lane_index_for_commit.get(head))
+                {
+                    commit_id_lanes[index].branch_names.push(name);
+                }
+            }
+        }
         // Find the first and last of lanes commits
         let first_commits =
             commit_id_lanes
@@ -105,35 +240,66 @@ impl View {
             // Get the parents
             let mut parents: Vec<Parent> = Vec::new();
             for parent in &state_commit.parents {
+                let parent_commit = commits.get(parent).unwrap().clone();
+                // An edge is only emphasized when it connects two emphasized commits,
+                // and faded when it leads to a faded commit
+                let style = if parent_commit.style == model::STYLE_FADED {
+                    model::STYLE_FADED.to_string()
+                } else if state_commit.style == model::STYLE_HIGHLIGHT
+                    && parent_commit.style != model::STYLE_HIGHLIGHT
+                {
+                    "".to_string()
+                } else {
+                    state_commit.style.clone()
+                };
                 parents.push(Parent {
-                    commit: commits.get(parent).unwrap().clone(),
-                    style: "".to_string(),
+                    commit: parent_commit,
+                    style,
                     ends_lane: first_commits.contains(parent),
                     begins_lane: last_commits.contains(id),
                     in_lane: lane_index_for_commit.get(id) == lane_index_for_commit.get(parent),
                 });
             }
 
+            let row = match rows.and_then(|r| r.get(id)) {
+                Some(&row) => row,
+                None => View::commit_row(&options.row_mode, state_commit.time, &parents),
+            };
             commits.insert(
                 id.clone(),
                 Rc::new(Commit {
                     id: state_commit.id.clone(),
                     hash: state_commit.id.clone(),
-                    message: "".to_string(),
+                    message: state_commit.message.clone(),
+                    tags: state_commit.tags.clone(),
                     time: state_commit.time,
-                    style: "".to_string(),
-                    parents: parents,
+                    row,
+                    style: state_commit.style.clone(),
+                    squashed: state_commit
+                        .squashed
+                        .iter()
+                        .filter_map(|id| commits.get(id).cloned())
+                        .collect(),
+                    elided_parents: state_commit.elided_parents.clone(),
+                    collapsed: runs.get(id).cloned().unwrap_or_default(),
+                    notes: state_commit.notes.clone(),
+                    parents,
                 }),
             );
         }
 
+        // Find the columns of the lanes
+        let spans = View::lane_spans(&commit_id_lanes, &commits, &lane_index_for_commit, options);
+        let columns = allocate_columns(&spans, options);
+
         // Make the lanes
         let lanes = commit_id_lanes
             .iter()
-            .map(|l| {
+            .zip(columns)
+            .map(|(l, col)| {
                 Rc::new(Lane {
                     branch_names: l.branch_names.clone(),
-                    col: l.priority,
+                    col,
                     commits: l
                         .commit_ids
                         .iter()
@@ -155,13 +321,62 @@ impl View {
                         .as_ref()
                         .and_then(|id| commits.get(id).cloned()),
                     style: "".to_string(),
+                    priority: priorities[&branch.name],
+                    checked_out: state.current_branch() == Some(&branch.name),
+                    remote: branch.remote.clone(),
                 }),
             );
         }
 
-        // Branch heads
+        let commits_branch_heads = View::branch_heads(&branches);
+
+        let head = match &state.head {
+            Some(model::Head::Branch(name)) => branches.get(name).cloned().map(Head::Branch),
+            Some(model::Head::Detached(id)) => commits.get(id).cloned().map(Head::Detached),
+            None => None,
+        };
+
+        // The groups, of the commits as they are shown
+        let mut groups = Vec::new();
+        for group in &state.groups {
+            let mut shown: Vec<Rc<Commit>> = Vec::new();
+            for id in &group.commits {
+                let commit = commits.get(id).or_else(|| {
+                    runs.iter()
+                        .find(|(_, run)| run.contains(id))
+                        .and_then(|(shown_id, _)| commits.get(shown_id))
+                });
+                if let Some(commit) = commit {
+                    if !shown.iter().any(|c| c.id == commit.id) {
+                        shown.push(commit.clone());
+                    }
+                }
+            }
+            if !shown.is_empty() {
+                groups.push(Group {
+                    name: group.name.clone(),
+                    commits: shown,
+                });
+            }
+        }
+
+        View {
+            commits,
+            branches,
+            commits_branch_heads,
+            lanes,
+            head,
+            moved_refs: Vec::new(),
+            groups,
+        }
+    }
+
+    // The branches pointing to each commit
+    pub(super) fn branch_heads(
+        branches: &HashMap<String, Rc<Branch>>,
+    ) -> HashMap<String, Vec<Rc<Branch>>> {
         let mut commits_branch_heads: HashMap<String, Vec<Rc<Branch>>> = HashMap::new();
-        for (name, branch) in &branches {
+        for branch in branches.values() {
             if let Some(commit) = &branch.head {
                 if let Some(heads) = commits_branch_heads.get_mut(&commit.id) {
                     heads.push(branch.clone());
@@ -170,12 +385,338 @@ impl View {
                 }
             }
         }
+        // The checked out branch first, then in branch order
+        for heads in commits_branch_heads.values_mut() {
+            heads.sort_by_key(|b| (!b.checked_out, b.priority));
+        }
+        commits_branch_heads
+    }
+
+    // The unreachable commits replaced by a squash, and the unreachable commits made on top of them
+    fn hidden_squashed(state: &Repository) -> HashSet<String> {
+        let reachable = state.reachable_commits();
+        let squashed = state
+            .commits
+            .values()
+            .flat_map(|c| c.squashed.iter())
+            .collect::<HashSet<&String>>();
+        let mut ordered = state.commits.values().collect::<Vec<&model::Commit>>();
+        ordered.sort_by_key(|c| c.time);
+        let mut hidden = HashSet::new();
+        for commit in ordered {
+            if !reachable.contains(&commit.id)
+                && (squashed.contains(&commit.id)
+                    || commit.parents.iter().any(|p| hidden.contains(p)))
+            {
+                hidden.insert(commit.id.clone());
+            }
+        }
+        hidden
+    }
+
+    // Key to sort the branches in the configured order
+    fn order_key(
+        state: &Repository,
+        branch: &model::Branch,
+        options: &LayoutOptions,
+    ) -> (bool, usize, usize, bool) {
+        // Remote-tracking branches are ordered like their local branch, if there is one
+        let local = branch
+            .remote
+            .as_ref()
+            .and_then(|remote| branch.name.strip_prefix(&format!("{}/", remote)))
+            .and_then(|name| state.branches.get(name))
+            .unwrap_or(branch);
+        let is_remote = branch.remote.is_some();
+        match options.remote_refs {
+            RemoteRefs::BesideLocal => (
+                false,
+                options.branch_order.group(&local.name),
+                local.priority,
+                is_remote,
+            ),
+            RemoteRefs::Separate => (
+                is_remote,
+                options.branch_order.group(&branch.name),
+                branch.priority,
+                false,
+            ),
+        }
+    }
+
+    // The commits of a lane, following the first parents from the head
+    // as long as they have been committed to the given branch
+    fn lane_commit_ids<'a>(
+        state: &'a Repository,
+        head: Option<&'a String>,
+        branch_name: &str,
+    ) -> Vec<&'a String> {
+        let mut head = head;
+        let mut lane_commits: Vec<&String> = Vec::new();
+        while let Some(commit_id) = head {
+            let state_commit = state.commits.get(commit_id).unwrap();
+            if state_commit.branch == branch_name {
+                lane_commits.push(commit_id);
+            }
+            head = state_commit.parents.first();
+        }
+        lane_commits
+    }
+
+    // The rows every lane occupies, including the connectors that are drawn in its column
+    fn lane_spans(
+        lanes: &[LaneWithCommitIds],
+        commits: &HashMap<String, Rc<Commit>>,
+        lane_index_for_commit: &HashMap<String, usize>,
+        options: &LayoutOptions,
+    ) -> Vec<LaneSpan> {
+        let mut spans = lanes
+            .iter()
+            .map(|l| LaneSpan {
+                branch_names: l.branch_names.clone(),
+                priority: l.priority,
+                group: l
+                    .branch_names
+                    .iter()
+                    .map(|name| options.branch_order.group(name))
+                    .min()
+                    .unwrap_or_else(|| options.branch_order.group(DETACHED_HEAD)),
+                rows: l
+                    .commit_ids
+                    .iter()
+                    .map(|&id| commits[id].row)
+                    .fold(None, |r, row| match r {
+                        None => Some((row, row)),
+                        Some((first, last)) => Some((first.min(row), last.max(row))),
+                    }),
+                source_lane: None,
+            })
+            .collect::<Vec<LaneSpan>>();
+        for commit in commits.values() {
+            for (index, parent) in commit.parents.iter().enumerate() {
+                if parent.in_lane {
+                    continue;
+                }
+                let (span_lane, row) = match (parent.begins_lane, parent.ends_lane) {
+                    // Branching off is drawn in the column of the new lane
+                    (true, false) => {
+                        let lane = lane_index_for_commit[&commit.id];
+                        if index == 0 {
+                            spans[lane].source_lane =
+                                lane_index_for_commit.get(&parent.commit.id).cloned();
+                        }
+                        (lane, parent.commit.row)
+                    }
+                    // Merging is drawn in the column of the merged lane
+                    (false, true) => (lane_index_for_commit[&parent.commit.id], commit.row),
+                    _ => continue,
+                };
+                let span = &mut spans[span_lane];
+                span.rows = span
+                    .rows
+                    .map(|(first, last)| (first.min(row), last.max(row)));
+            }
+        }
+        spans
+    }
 
-        View {
-            commits,
-            branches,
-            commits_branch_heads,
-            lanes,
+    // The row a commit is drawn in.
+    // The parents must already have their rows, which is the case
+    // because commits are created in time order.
+    fn commit_row(mode: &RowMode, time: usize, parents: &[Parent]) -> usize {
+        match mode {
+            RowMode::Chronological => time,
+            RowMode::Compact => parents.iter().map(|p| p.commit.row + 1).max().unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rows(view: &View) -> HashMap<String, usize> {
+        view.commits
+            .iter()
+            .map(|(id, c)| (id.clone(), c.row))
+            .collect()
+    }
+
+    fn two_feature_branches() -> Repository {
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.checkout_branch("feature1");
+        repo.commit("B".to_string());
+        repo.commit("C".to_string());
+        repo.checkout_branch_at_commit("feature2", "A");
+        repo.commit("D".to_string());
+        repo.checkout_branch("main");
+        repo.merge(
+            "M".to_string(),
+            &["feature1".to_string(), "feature2".to_string()],
+        )
+        .unwrap();
+        repo
+    }
+
+    #[test]
+    fn chronological_rows() {
+        // Setup
+        let repo = two_feature_branches();
+
+        // Act
+        let view = View::from_state(&repo, &LayoutOptions::default());
+
+        // Test
+        let rows = rows(&view);
+        assert_eq!(rows["A"], 0);
+        assert_eq!(rows["C"], 2);
+        assert_eq!(rows["D"], 3);
+        assert_eq!(rows["M"], 4);
+    }
+
+    #[test]
+    fn compact_rows() {
+        // Setup
+        let repo = two_feature_branches();
+        let options = LayoutOptions {
+            row_mode: RowMode::Compact,
+            ..LayoutOptions::default()
+        };
+
+        // Act
+        let view = View::from_state(&repo, &options);
+
+        // Test
+        let rows = rows(&view);
+        assert_eq!(rows["A"], 0);
+        assert_eq!(rows["B"], 1);
+        assert_eq!(rows["D"], 1);
+        assert_eq!(rows["C"], 2);
+        assert_eq!(rows["M"], 3);
+    }
+
+    #[test]
+    fn branches_share_lane() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.add_branch("release".to_string(), None);
+        repo.commit("B".to_string());
+        repo.add_branch("backup".to_string(), None);
+
+        // Act
+        let view = View::from_state(&repo, &LayoutOptions::default());
+
+        // Test
+        assert_eq!(view.lanes.len(), 1);
+        assert_eq!(
+            view.lanes[0].branch_names,
+            vec!["main", "release", "backup"]
+        );
+        assert_eq!(view.lanes[0].commits.len(), 2);
+    }
+
+    #[test]
+    fn detached_head() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.commit("B".to_string());
+        repo.checkout("A");
+        repo.commit("C".to_string());
+
+        // Act
+        let view = View::from_state(&repo, &LayoutOptions::default());
+
+        // Test
+        assert_eq!(view.lanes.len(), 2);
+        assert!(view.lanes[1].branch_names.is_empty());
+        assert_eq!(view.lanes[1].commits[0].id, "C");
+        match &view.head {
+            Some(Head::Detached(commit)) => assert_eq!(commit.id, "C"),
+            _ => panic!("HEAD is not detached"),
         }
+        assert!(!view.branches["main"].checked_out);
+    }
+
+    #[test]
+    fn remote_refs_beside_local() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.checkout_branch("feature");
+        repo.commit("B".to_string());
+        repo.push("origin", None);
+        repo.checkout_branch("main");
+        repo.push("origin", None);
+        repo.fetch("origin", "main", &["C".to_string()]);
+
+        // Act
+        let view = View::from_state(&repo, &LayoutOptions::default());
+
+        // Test
+        let priority = |name: &str| view.branches[name].priority;
+        assert!(priority("main") < priority("origin/main"));
+        assert!(priority("origin/main") < priority("feature"));
+        assert!(priority("feature") < priority("origin/feature"));
+        assert_eq!(
+            view.branches["origin/main"].remote,
+            Some("origin".to_string())
+        );
+        // Fetched commits get their own lane, origin/feature shares the feature lane
+        assert_eq!(view.lanes.len(), 3);
+    }
+
+    #[test]
+    fn squashed_commits() {
+        // Setup
+        let mut repo = Repository::default();
+        repo.commit("A".to_string());
+        repo.commit("B".to_string());
+        repo.commit("C".to_string());
+        repo.squash("S".to_string(), "B", "C").unwrap();
+        let mut options = LayoutOptions::default();
+
+        // Act
+        let collapsed = View::from_state(&repo, &options);
+        options.squash_display = SquashDisplay::Expanded;
+        let expanded = View::from_state(&repo, &options);
+
+        // Test
+        assert_eq!(collapsed.commits.len(), 2);
+        assert!(collapsed.commits["S"].squashed.is_empty());
+        assert_eq!(rows(&collapsed)["S"], 1);
+        assert_eq!(expanded.commits.len(), 4);
+        let squashed = &expanded.commits["S"].squashed;
+        assert_eq!(
+            squashed.iter().map(|c| &c.id).collect::<Vec<_>>(),
+            vec!["B", "C"]
+        );
+        // The replaced commits are on their own lane
+        assert_eq!(expanded.lanes.len(), 2);
+        assert!(expanded.lanes[1].branch_names.is_empty());
+    }
+
+    #[test]
+    fn highlighted_edges() {
+        // Setup
+        let mut repo = Repository::default();
+        for id in ["A", "B", "C"] {
+            repo.commit(id.to_string());
+        }
+        repo.highlight(&model::Highlight::Commits(vec![
+            "B".to_string(),
+            "C".to_string(),
+        ]))
+        .unwrap();
+
+        // Act
+        let view = View::from_state(&repo, &LayoutOptions::default());
+
+        // Test
+        let edge_style = |id: &str| view.commits[id].parents[0].style.clone();
+        assert_eq!(edge_style("C"), model::STYLE_HIGHLIGHT);
+        assert_eq!(edge_style("B"), model::STYLE_FADED);
     }
 }